@@ -0,0 +1,271 @@
+//! Frequency analysis of the texts encrypted with a classical cipher, to recover their key.
+
+use clap::ValueEnum;
+use deunicode::deunicode;
+use std::collections::HashMap;
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    German,
+    Spanish,
+}
+
+impl Language {
+    /// The frequencies of the letters from `a` to `z`, in percent, the accents removed.
+    fn frequencies(self) -> [f64; 26] {
+        match self {
+            Language::English => [
+                8.167, 1.492, 2.782, 4.253, 12.702, 2.228, 2.015, 6.094, 6.966, 0.153, 0.772,
+                4.025, 2.406, 6.749, 7.507, 1.929, 0.095, 5.987, 6.327, 9.056, 2.758, 0.978, 2.360,
+                0.150, 1.974, 0.074,
+            ],
+            Language::French => [
+                7.636, 0.901, 3.260, 3.669, 14.715, 1.066, 0.866, 0.737, 7.529, 0.613, 0.074,
+                5.456, 2.968, 7.095, 5.796, 2.521, 1.362, 6.693, 7.948, 7.244, 6.311, 1.838, 0.049,
+                0.427, 0.128, 0.326,
+            ],
+            Language::German => [
+                6.516, 1.886, 2.732, 5.076, 16.396, 1.656, 3.009, 4.577, 6.550, 0.268, 1.417,
+                3.437, 2.534, 9.776, 2.594, 0.670, 0.018, 7.003, 7.270, 6.154, 4.166, 0.846, 1.921,
+                0.034, 0.039, 1.134,
+            ],
+            Language::Spanish => [
+                11.525, 2.215, 4.019, 5.010, 12.181, 0.692, 1.768, 0.703, 6.247, 0.493, 0.011,
+                4.967, 3.157, 6.712, 8.683, 2.510, 0.877, 6.871, 7.977, 4.632, 2.927, 1.138, 0.017,
+                0.215, 1.008, 0.467,
+            ],
+        }
+    }
+
+    /// The index of coincidence of a text in the language.
+    fn index_of_coincidence(self) -> f64 {
+        self.frequencies()
+            .iter()
+            .map(|frequency| (frequency / 100.0).powi(2))
+            .sum()
+    }
+}
+
+/// The index of coincidence of a text of random letters.
+const RANDOM_INDEX_OF_COINCIDENCE: f64 = 1.0 / 26.0;
+
+/// A candidate key of a Caesar cipher.
+#[derive(Debug, PartialEq)]
+pub struct ShiftCandidate {
+    /// The shift to decrypt the text with
+    pub shift: u8,
+
+    /// The chi-squared distance to the letter frequencies of the language, the lower the better
+    pub score: f64,
+}
+
+/// The recovered key of a Vigenère cipher.
+#[derive(Debug, PartialEq)]
+pub struct VigenereKey {
+    /// The key, in lowercase letters
+    pub key: String,
+
+    /// The average index of coincidence of the columns of the text for the key length
+    pub index_of_coincidence: f64,
+}
+
+/// The positions in the alphabet (0 for `a`) of the letters of the text, the other characters removed.
+pub fn letters(text: &str) -> Vec<u8> {
+    deunicode(text)
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase() as u8 - b'a')
+        .collect()
+}
+
+/// Score every shift of a text encrypted with a Caesar cipher, the best candidates first.
+pub fn crack_caesar(letters: &[u8], language: Language) -> Vec<ShiftCandidate> {
+    let mut candidates: Vec<ShiftCandidate> = (0..26)
+        .map(|shift| ShiftCandidate {
+            shift,
+            score: chi_squared(
+                letters.iter().map(|letter| (letter + 26 - shift) % 26),
+                language,
+            ),
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| a.score.total_cmp(&b.score));
+
+    candidates
+}
+
+/// Recover the key of a text encrypted with a Vigenère cipher.
+/// The key length is the shortest one whose columns have an index of coincidence close to the one
+/// of the language, as the multiples of the key length score as well (or the best one if none is close).
+/// Each column is then cracked as a Caesar cipher.
+/// Returns `None` if the text is too short to be analysed.
+pub fn crack_vigenere(
+    letters: &[u8],
+    language: Language,
+    max_key_length: usize,
+) -> Option<VigenereKey> {
+    let max_key_length = max_key_length.min(letters.len() / 2);
+
+    let scores: Vec<(usize, f64)> = (1..=max_key_length)
+        .map(|length| (length, columns_index_of_coincidence(letters, length)))
+        .collect();
+
+    // Between the index of coincidence of random letters and the one of the language
+    let threshold = RANDOM_INDEX_OF_COINCIDENCE
+        + 0.6 * (language.index_of_coincidence() - RANDOM_INDEX_OF_COINCIDENCE);
+
+    let (length, index_of_coincidence) = *scores
+        .iter()
+        .find(|(_, score)| *score >= threshold)
+        .or_else(|| scores.iter().max_by(|a, b| a.1.total_cmp(&b.1)))?;
+
+    let key = (0..length)
+        .map(|column| {
+            let column: Vec<u8> = letters
+                .iter()
+                .skip(column)
+                .step_by(length)
+                .copied()
+                .collect();
+            let shift = crack_caesar(&column, language)[0].shift;
+
+            (b'a' + shift) as char
+        })
+        .collect();
+
+    Some(VigenereKey {
+        key,
+        index_of_coincidence,
+    })
+}
+
+/// The Kasiski examination: the distances between the repeated trigrams are multiples of the key
+/// length, so the most frequent factors of the distances are the likely key lengths.
+/// Returns the factors from 2 to `max_key_length` with their number of distances, the most frequent first.
+pub fn kasiski(letters: &[u8], max_key_length: usize) -> Vec<(usize, usize)> {
+    let mut positions: HashMap<&[u8], usize> = HashMap::new();
+    let mut factors: HashMap<usize, usize> = HashMap::new();
+
+    for (position, trigram) in letters.windows(3).enumerate() {
+        if let Some(previous) = positions.insert(trigram, position) {
+            let distance = position - previous;
+
+            for factor in 2..=max_key_length.min(distance) {
+                if distance % factor == 0 {
+                    *factors.entry(factor).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut factors: Vec<(usize, usize)> = factors.into_iter().collect();
+    factors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    factors
+}
+
+/// The probability that two letters picked at random in the text are the same.
+/// About 0.067 for an English text, and 0.038 for random letters.
+pub fn index_of_coincidence(letters: impl Iterator<Item = u8>) -> f64 {
+    let counts = count(letters);
+    let total: f64 = counts.iter().sum();
+
+    if total < 2.0 {
+        return 0.0;
+    }
+
+    counts
+        .iter()
+        .map(|count| count * (count - 1.0))
+        .sum::<f64>()
+        / (total * (total - 1.0))
+}
+
+/// The average index of coincidence of the columns of the text split with the key length.
+fn columns_index_of_coincidence(letters: &[u8], length: usize) -> f64 {
+    let sum: f64 = (0..length)
+        .map(|column| index_of_coincidence(letters.iter().skip(column).step_by(length).copied()))
+        .sum();
+
+    sum / length as f64
+}
+
+/// The chi-squared distance between the letter counts and the expected counts for the language.
+fn chi_squared(letters: impl Iterator<Item = u8>, language: Language) -> f64 {
+    let counts = count(letters);
+    let total: f64 = counts.iter().sum();
+
+    counts
+        .iter()
+        .zip(language.frequencies())
+        .map(|(count, frequency)| {
+            let expected = total * frequency / 100.0;
+            (count - expected).powi(2) / expected
+        })
+        .sum()
+}
+
+fn count(letters: impl Iterator<Item = u8>) -> [f64; 26] {
+    let mut counts = [0.0; 26];
+
+    for letter in letters {
+        counts[letter as usize] += 1.0;
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::crypto::classic::Classic;
+    use rstest::rstest;
+
+    const ENGLISH: &str = "It was the best of times, it was the worst of times, it was the age of wisdom, \
+        it was the age of foolishness, it was the epoch of belief, it was the epoch of incredulity, \
+        it was the season of Light, it was the season of Darkness, it was the spring of hope, \
+        it was the winter of despair, we had everything before us, we had nothing before us, \
+        we were all going direct to Heaven, we were all going direct the other way.";
+
+    const FRENCH: &str = "Longtemps, je me suis couché de bonne heure. Parfois, à peine ma bougie éteinte, \
+        mes yeux se fermaient si vite que je n'avais pas le temps de me dire : « Je m'endors. »";
+
+    #[rstest]
+    #[case(ENGLISH, Language::English, 7)]
+    #[case(ENGLISH, Language::English, 20)]
+    #[case(FRENCH, Language::French, 11)]
+    fn test_crack_caesar(#[case] text: &str, #[case] language: Language, #[case] shift: u8) {
+        let encrypted: Vec<u8> = letters(text).iter().map(|l| (l + shift) % 26).collect();
+
+        assert_eq!(crack_caesar(&encrypted, language)[0].shift, shift);
+    }
+
+    #[rstest]
+    #[case("lemon")]
+    #[case("dickens")]
+    #[case("k")]
+    fn test_crack_vigenere(#[case] key: &str) {
+        let encrypted = Classic::vigenere(key).unwrap().encrypt(ENGLISH);
+
+        let cracked = crack_vigenere(&letters(&encrypted), Language::English, 20).unwrap();
+
+        assert_eq!(cracked.key, key);
+    }
+
+    #[test]
+    fn test_kasiski() {
+        let encrypted = Classic::vigenere("lemon").unwrap().encrypt(ENGLISH);
+
+        assert_eq!(kasiski(&letters(&encrypted), 20)[0].0, 5);
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert!(index_of_coincidence(letters(ENGLISH).into_iter()) > 0.06);
+        assert!(index_of_coincidence((0..26).cycle().take(260)) < 0.04);
+    }
+}
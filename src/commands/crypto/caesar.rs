@@ -1,7 +1,11 @@
-use clap::{Args, builder};
+use clap::{Args, ValueEnum, builder};
 use deunicode::deunicode;
+use std::io::{BufRead, Read, Write, stdout};
 
 use super::Cipher;
+use super::analysis::{Language, ShiftCandidate, crack_caesar, letters};
+use super::input::{Input, InputCmdOpt};
+use crate::utils::alphabet::{ALPHABET_LOWER, ALPHABET_UPPER, NUMERIC};
 
 #[derive(Args, Clone)]
 pub struct Command {
@@ -15,78 +19,361 @@ pub struct Command {
     cipher: Cipher,
 
     #[arg(
-        required = true,
+        default_value = "-",
+        hide_default_value = true,
         value_parser = builder::NonEmptyStringValueParser::new(),
-        help = "The string to encrypt or decrypt."
+        help = "The string to encrypt or decrypt. Use - or omit it to transform the text read from stdin, line by line."
     )]
     value: String,
 
+    #[clap(flatten)]
+    input: InputCmdOpt,
+
     #[arg(
         short,
         long,
         default_value_t = 3,
         value_parser = builder::RangedI64ValueParser::<i64>::new(),
         allow_hyphen_values = true,
+        conflicts_with = "crack",
         help = "The shift value to use for the Caesar cipher."
     )]
     shift: i64,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Don't transliterate the text to ASCII, the characters out of the alphabet are kept as they are."
+    )]
+    preserve_unicode: bool,
+
+    #[arg(
+        long,
+        default_value = "latin",
+        value_enum,
+        ignore_case = true,
+        help = "The alphabet in which the characters are shifted."
+    )]
+    alphabet: CaesarAlphabet,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        conflicts_with = "alphabet",
+        help = "Shift the characters in the given alphabet instead, such as \"abcdefghijklmnopqrstuvwxyz0123456789\"."
+    )]
+    custom_alphabet: Option<String>,
+
+    #[clap(flatten)]
+    crack: ArgsCrack,
+
+    #[arg(
+        long,
+        default_value_t = 3,
+        value_parser = builder::RangedU64ValueParser::<usize>::new().range(1..=26),
+        help = "The number of candidates to print with --crack."
+    )]
+    candidates: usize,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum CaesarAlphabet {
+    /// The 26 letters, keeping their case
+    #[default]
+    Latin,
+
+    /// The lowercase letters then the digits
+    LowercaseDigits,
+
+    /// The uppercase letters, the lowercase letters then the digits
+    Alphanumeric,
+}
+
+impl CaesarAlphabet {
+    /// Returns the characters of the alphabet in order, `None` for the latin letters keeping their case.
+    fn chars(self) -> Option<Vec<char>> {
+        match self {
+            CaesarAlphabet::Latin => None,
+            CaesarAlphabet::LowercaseDigits => Some([&ALPHABET_LOWER[..], &NUMERIC[..]].concat()),
+            CaesarAlphabet::Alphanumeric => {
+                Some([&ALPHABET_UPPER[..], &ALPHABET_LOWER[..], &NUMERIC[..]].concat())
+            }
+        }
+    }
+}
+
+/// The options to decrypt a text without its key, shared by the ciphers that can be cracked.
+#[derive(Args, Clone, Default)]
+pub struct ArgsCrack {
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Decrypt without the key, by frequency analysis of the letters. The whole input is read before."
+    )]
+    pub crack: bool,
+
+    #[arg(
+        short,
+        long,
+        default_value = "english",
+        value_enum,
+        ignore_case = true,
+        help = "The language of the text, for --crack."
+    )]
+    pub language: Language,
+}
+
+impl ArgsCrack {
+    /// Read the whole input to analyse it, returning `None` after printing the error if it can't be cracked.
+    pub(super) fn read(&self, cipher: &Cipher, input: &Input) -> Option<String> {
+        if *cipher == Cipher::Encrypt {
+            eprintln!("The --crack option can only be used to decrypt.");
+            return None;
+        }
+
+        let mut text = String::new();
+
+        if let Err(e) = input
+            .reader()
+            .and_then(|mut reader| reader.read_to_string(&mut text))
+        {
+            eprintln!("Error reading the input: {e}");
+            return None;
+        }
+
+        // The candidates are printed with a line break
+        if text.ends_with('\n') {
+            text.pop();
+
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+
+        if letters(&text).is_empty() {
+            eprintln!("The input has no letters to analyse.");
+            return None;
+        }
+
+        Some(text)
+    }
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command {
+            cipher: Cipher::Encrypt,
+            value: "-".to_string(),
+            input: InputCmdOpt::default(),
+            shift: 3,
+            preserve_unicode: false,
+            alphabet: CaesarAlphabet::default(),
+            custom_alphabet: None,
+            crack: ArgsCrack::default(),
+            candidates: 3,
+        }
+    }
 }
 
 impl Command {
     pub fn execute(&self) {
-        let string = match self.cipher {
-            Cipher::Encrypt => self.encrypt(),
-            Cipher::Decrypt => self.decrypt(),
+        if let Err(e) = self.check_alphabet() {
+            eprintln!("{e}");
+            return;
+        }
+
+        let input = self.input.input(Some(&self.value));
+
+        if self.crack.crack {
+            if self.alphabet_chars().is_some() {
+                eprintln!("The --crack option can only be used with the latin alphabet.");
+                return;
+            }
+
+            if let Some(text) = self.crack.read(&self.cipher, &input) {
+                for (candidate, decrypted) in self.crack(&text).iter().take(self.candidates) {
+                    println!(
+                        "Shift {} (chi-squared {:.2})",
+                        candidate.shift, candidate.score
+                    );
+                    println!("{decrypted}");
+                }
+            }
+
+            return;
+        }
+
+        if let Input::Value(_) = &input {
+            let string = match self.cipher {
+                Cipher::Encrypt => self.encrypt(),
+                Cipher::Decrypt => self.decrypt(),
+            };
+
+            println!("{string}");
+            return;
+        }
+
+        let reader = match input.reader() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Error reading the input: {e}");
+                return;
+            }
         };
 
-        println!("{string}");
+        if let Err(e) = self.transform_lines(reader) {
+            eprintln!("Error transforming the input: {e}");
+        }
     }
 
-    pub fn encrypt(&self) -> String {
-        let value = deunicode(&self.value);
-
-        #[allow(clippy::cast_possible_truncation)]
-        // rem_euclid returns a value in the range of 0..26
-        let shift = self.shift.rem_euclid(26) as u8;
-        let mut result = String::new();
-
-        for c in value.chars() {
-            if c.is_ascii_alphabetic() {
-                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
-                let offset = c as u8 - base;
-                let shifted = (offset + shift) % 26;
-                result.push((shifted + base) as char);
-            } else {
-                result.push(c);
-            }
+    /// Transform the text read from the reader line by line, writing each line as soon as it is read,
+    /// so the memory used doesn't depend on the size of the input.
+    fn transform_lines(&self, mut reader: impl BufRead) -> std::io::Result<()> {
+        let mut stdout = stdout().lock();
+        let mut line = String::new();
+
+        while reader.read_line(&mut line)? > 0 {
+            // The line break is not alphabetic, so it is kept as it is
+            stdout.write_all(self.transform(&line).as_bytes())?;
+            line.clear();
         }
 
-        result
+        stdout.flush()
+    }
+
+    /// Decrypt the text with every shift, the most likely first.
+    fn crack(&self, text: &str) -> Vec<(ShiftCandidate, String)> {
+        crack_caesar(&letters(text), self.crack.language)
+            .into_iter()
+            .map(|candidate| {
+                let command = Command {
+                    shift: i64::from(candidate.shift),
+                    ..self.clone()
+                };
+                let decrypted = command.decrypt_text(text);
+
+                (candidate, decrypted)
+            })
+            .collect()
+    }
+
+    /// Encrypt or decrypt a line read from the input, instead of the value.
+    fn transform(&self, value: &str) -> String {
+        match self.cipher {
+            Cipher::Encrypt => self.shift_text(value, self.shift),
+            Cipher::Decrypt => self.decrypt_text(value),
+        }
+    }
+
+    pub fn encrypt(&self) -> String {
+        self.shift_text(&self.value, self.shift)
     }
 
     pub fn decrypt(&self) -> String {
-        let value = deunicode(&self.value);
-
-        #[allow(clippy::cast_possible_truncation)]
-        // rem_euclid returns a value in the range of 0..26
-        let shift = self.shift.rem_euclid(26) as u8;
-        let mut result = String::new();
-
-        for c in value.chars() {
-            if c.is_ascii_alphabetic() {
-                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
-                let offset = c as u8 - base;
-                let shifted = (offset + 26 - shift) % 26;
-                result.push((shifted + base) as char);
-            } else {
-                result.push(c);
+        self.decrypt_text(&self.value)
+    }
+
+    fn decrypt_text(&self, value: &str) -> String {
+        // Shifting back by the shift is shifting forward by its opposite, modulo the alphabet length
+        let length = self.alphabet_chars().map_or(26, |alphabet| alphabet.len()) as i64;
+
+        self.shift_text(value, length - self.shift.rem_euclid(length))
+    }
+
+    fn shift_text(&self, value: &str, shift: i64) -> String {
+        let value = if self.preserve_unicode {
+            value.to_string()
+        } else {
+            deunicode(value)
+        };
+
+        match self.alphabet_chars() {
+            None => {
+                #[allow(clippy::cast_possible_truncation)]
+                // rem_euclid returns a value in the range of 0..26
+                let shift = shift.rem_euclid(26) as u8;
+
+                value.chars().map(|c| shift_letter(c, shift)).collect()
             }
+            Some(alphabet) => {
+                #[allow(clippy::cast_possible_truncation)]
+                // rem_euclid returns a value in the range of the alphabet length
+                let shift = shift.rem_euclid(alphabet.len() as i64) as usize;
+
+                value
+                    .chars()
+                    .map(|c| match alphabet.iter().position(|&a| a == c) {
+                        Some(position) => alphabet[(position + shift) % alphabet.len()],
+                        None => c,
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the characters in which the text is shifted, in order.
+    /// `None` means the ASCII letters keeping their case. The other characters are kept as they are.
+    fn alphabet_chars(&self) -> Option<Vec<char>> {
+        match &self.custom_alphabet {
+            Some(custom_alphabet) => Some(custom_alphabet.chars().collect()),
+            None => self.alphabet.chars(),
+        }
+    }
+
+    /// Check that the custom alphabet can be used to shift the characters and shift them back.
+    fn check_alphabet(&self) -> Result<(), String> {
+        let Some(alphabet) = self.alphabet_chars() else {
+            return Ok(());
+        };
+
+        if alphabet.len() < 2 {
+            return Err("The alphabet must contain at least 2 characters.".to_string());
+        }
+
+        if let Some((i, c)) = alphabet
+            .iter()
+            .enumerate()
+            .find(|(i, c)| alphabet[..*i].contains(c))
+            .map(|(i, c)| (i, *c))
+        {
+            return Err(format!(
+                "The character {c:?} is in the alphabet twice (at position {}).",
+                i + 1
+            ));
+        }
+
+        if !self.preserve_unicode && !alphabet.iter().all(char::is_ascii) {
+            return Err(
+                "The text is transliterated to ASCII, use --preserve-unicode to shift the characters out of ASCII."
+                    .to_string(),
+            );
         }
 
-        result
+        Ok(())
     }
 }
 
+/// Shift an ASCII letter by `shift` positions in the alphabet, keeping its case.
+/// The other characters are returned as they are.
+pub fn shift_letter(c: char, shift: u8) -> char {
+    map_letter(c, |offset| (offset + shift % 26) % 26)
+}
+
+/// Replace an ASCII letter by the letter at the position returned by `map` for its position
+/// in the alphabet (0 for `a`), keeping its case. The other characters are returned as they are.
+/// `map` must return a position in the range of 0..26.
+pub fn map_letter(c: char, map: impl Fn(u8) -> u8) -> char {
+    if !c.is_ascii_alphabetic() {
+        return c;
+    }
+
+    let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+
+    (map(c as u8 - base) + base) as char
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,6 +389,7 @@ mod tests {
             cipher: Cipher::Encrypt,
             value: value.to_string(),
             shift,
+            ..Command::default()
         };
 
         assert_eq!(command.encrypt(), expected);
@@ -117,8 +405,90 @@ mod tests {
             cipher: Cipher::Decrypt,
             value: value.to_string(),
             shift,
+            ..Command::default()
         };
 
         assert_eq!(command.decrypt(), expected);
     }
+
+    #[rstest]
+    #[case("Wkh txlfn eurzq ira mxpsv ryhu wkh odcb grj.", 3)]
+    #[case("Pm ol ohk hufaopun jvumpkluaphs av zhf, ol dyval pa pu jpwoly.", 7)]
+    fn test_crack(#[case] value: &str, #[case] shift: u8) {
+        let command = Command {
+            cipher: Cipher::Decrypt,
+            value: value.to_string(),
+            ..Command::default()
+        };
+
+        let (candidate, decrypted) = &command.crack(value)[0];
+
+        assert_eq!(candidate.shift, shift);
+        assert_eq!(
+            *decrypted,
+            Command {
+                shift: i64::from(shift),
+                ..command.clone()
+            }
+            .decrypt()
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "Élodie, Ωmega",
+        false,
+        CaesarAlphabet::Latin,
+        None,
+        3,
+        "Horglh, Rphjd"
+    )]
+    #[case("Élodie, Ωmega", true, CaesarAlphabet::Latin, None, 3, "Éorglh, Ωphjd")]
+    #[case("zoé 42", true, CaesarAlphabet::LowercaseDigits, None, 5, "4té 97")]
+    #[case("Zz9", false, CaesarAlphabet::Alphanumeric, None, 1, "a0A")]
+    #[case("αβγ δ!", true, CaesarAlphabet::Latin, Some("αβγδ"), 1, "βγδ α!")]
+    fn test_alphabet(
+        #[case] value: &str,
+        #[case] preserve_unicode: bool,
+        #[case] alphabet: CaesarAlphabet,
+        #[case] custom_alphabet: Option<&str>,
+        #[case] shift: i64,
+        #[case] expected: &str,
+    ) {
+        let command = Command {
+            value: value.to_string(),
+            shift,
+            preserve_unicode,
+            alphabet,
+            custom_alphabet: custom_alphabet.map(str::to_string),
+            ..Command::default()
+        };
+
+        assert_eq!(command.check_alphabet(), Ok(()));
+        assert_eq!(command.encrypt(), expected);
+
+        if preserve_unicode {
+            let command = Command {
+                value: expected.to_string(),
+                ..command
+            };
+
+            assert_eq!(command.decrypt(), value);
+        }
+    }
+
+    #[rstest]
+    #[case(false, "a")]
+    #[case(false, "abca")]
+    #[case(false, "αβγ")]
+    #[case(true, "αα")]
+    fn test_invalid_alphabet(#[case] preserve_unicode: bool, #[case] custom_alphabet: &str) {
+        let command = Command {
+            preserve_unicode,
+            custom_alphabet: Some(custom_alphabet.to_string()),
+            ..Command::default()
+        };
+
+        assert!(command.check_alphabet().is_err());
+    }
 }
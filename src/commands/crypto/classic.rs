@@ -0,0 +1,506 @@
+use clap::{Args, Subcommand, builder};
+use deunicode::deunicode;
+use std::io::{BufRead, Write, stdout};
+
+use super::Cipher;
+use super::analysis::{crack_vigenere, kasiski, letters};
+use super::caesar::{ArgsCrack, map_letter, shift_letter};
+use super::input::{Input, InputCmdOpt};
+
+#[derive(Subcommand, Clone)]
+#[command(about = "Encrypt or decrypt a message using a classical cipher")]
+pub enum ClassicCmd {
+    #[command(about = "Shift each letter by the matching letter of a repeated key")]
+    Vigenere(VigenereCommand),
+
+    #[command(
+        about = "Replace each letter by the letter at the same position from the end of the alphabet"
+    )]
+    Atbash(ArgsClassic),
+
+    #[command(about = "Shift each letter by 13, so encrypting and decrypting are the same")]
+    Rot13(ArgsClassic),
+
+    #[command(about = "Rotate the printable ASCII characters (from ! to ~) by 47")]
+    Rot47(ArgsClassic),
+
+    #[command(
+        about = "Replace the letter at position x by the letter at position (multiplier * x + shift) mod 26"
+    )]
+    Affine(AffineCommand),
+
+    #[command(
+        about = "Write the text in zigzag on several rails, then read the rails one after the other"
+    )]
+    RailFence(RailFenceCommand),
+}
+
+/// The arguments shared by all the classical ciphers.
+#[derive(Args, Clone)]
+pub struct ArgsClassic {
+    #[arg(
+        value_enum,
+        action = clap::ArgAction::Set,
+        num_args = 1,
+        ignore_case = true,
+        help = "Specify the operation to perform."
+    )]
+    cipher: Cipher,
+
+    #[arg(
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The string to encrypt or decrypt. Use - or omit it to transform the text read from stdin, line by line."
+    )]
+    value: Option<String>,
+
+    #[clap(flatten)]
+    input: InputCmdOpt,
+}
+
+#[derive(Args, Clone)]
+pub struct VigenereCommand {
+    #[clap(flatten)]
+    args: ArgsClassic,
+
+    #[arg(
+        short,
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The key, only its letters are used. Required unless --crack is used."
+    )]
+    key: Option<String>,
+
+    #[clap(flatten)]
+    crack: ArgsCrack,
+
+    #[arg(
+        long,
+        default_value_t = 20,
+        value_parser = builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "The longest key to try with --crack."
+    )]
+    max_key_length: usize,
+}
+
+#[derive(Args, Clone)]
+pub struct AffineCommand {
+    #[clap(flatten)]
+    args: ArgsClassic,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 5,
+        value_parser = builder::RangedI64ValueParser::<i64>::new(),
+        allow_hyphen_values = true,
+        help = "The multiplier, it must be coprime with 26."
+    )]
+    multiplier: i64,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 8,
+        value_parser = builder::RangedI64ValueParser::<i64>::new(),
+        allow_hyphen_values = true,
+        help = "The shift added after the multiplication."
+    )]
+    shift: i64,
+}
+
+#[derive(Args, Clone)]
+pub struct RailFenceCommand {
+    #[clap(flatten)]
+    args: ArgsClassic,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 3,
+        value_parser = builder::RangedU64ValueParser::<usize>::new().range(2..),
+        help = "The number of rails."
+    )]
+    rails: usize,
+}
+
+/// A classical cipher, with its key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Classic {
+    /// The shifts of the letters of the key
+    Vigenere(Vec<u8>),
+    Atbash,
+    Rot13,
+    Rot47,
+    Affine {
+        multiplier: u8,
+        shift: u8,
+    },
+    RailFence(usize),
+}
+
+impl ClassicCmd {
+    pub fn execute(&self) {
+        let (args, classic) = match self {
+            ClassicCmd::Vigenere(command) => match command.classic() {
+                Some(classic) => (&command.args, classic),
+                None => {
+                    command.crack();
+                    return;
+                }
+            },
+            ClassicCmd::Atbash(args) => (args, Ok(Classic::Atbash)),
+            ClassicCmd::Rot13(args) => (args, Ok(Classic::Rot13)),
+            ClassicCmd::Rot47(args) => (args, Ok(Classic::Rot47)),
+            ClassicCmd::Affine(command) => (
+                &command.args,
+                Classic::affine(command.multiplier, command.shift),
+            ),
+            ClassicCmd::RailFence(command) => {
+                (&command.args, Ok(Classic::RailFence(command.rails)))
+            }
+        };
+
+        match classic {
+            Ok(classic) => args.execute(&classic),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+impl VigenereCommand {
+    /// Returns the cipher with the given key, or `None` if the key must be recovered with --crack.
+    fn classic(&self) -> Option<Result<Classic, String>> {
+        match (&self.key, self.crack.crack) {
+            (None, true) => None,
+            (Some(key), false) => Some(Classic::vigenere(key)),
+            (Some(_), true) => Some(Err(
+                "The --key option can't be used with --crack.".to_string()
+            )),
+            (None, false) => Some(Err(
+                "The --key option is required unless --crack is used.".to_string()
+            )),
+        }
+    }
+
+    /// Recover the key by frequency analysis, then decrypt the input with it.
+    fn crack(&self) {
+        let input = self.args.input.input(self.args.value.as_deref());
+
+        let Some(text) = self.crack.read(&self.args.cipher, &input) else {
+            return;
+        };
+
+        let letters = letters(&text);
+
+        let Some(cracked) = crack_vigenere(&letters, self.crack.language, self.max_key_length)
+        else {
+            eprintln!("The input is too short to find the key.");
+            return;
+        };
+
+        let kasiski_lengths: Vec<String> = kasiski(&letters, self.max_key_length)
+            .iter()
+            .take(3)
+            .map(|(length, _)| length.to_string())
+            .collect();
+
+        if !kasiski_lengths.is_empty() {
+            println!("Kasiski examination: {}", kasiski_lengths.join(", "));
+        }
+
+        println!(
+            "Key length: {} (index of coincidence {:.4})",
+            cracked.key.len(),
+            cracked.index_of_coincidence
+        );
+        println!("Key: {}", cracked.key);
+
+        match Classic::vigenere(&cracked.key) {
+            Ok(classic) => println!("{}", classic.decrypt(&text)),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+impl ArgsClassic {
+    fn execute(&self, classic: &Classic) {
+        let input = self.input.input(self.value.as_deref());
+
+        if let Input::Value(value) = &input {
+            println!("{}", classic.transform(&self.cipher, value));
+            return;
+        }
+
+        let reader = match input.reader() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Error reading the input: {e}");
+                return;
+            }
+        };
+
+        if let Err(e) = self.transform_lines(classic, reader) {
+            eprintln!("Error transforming the input: {e}");
+        }
+    }
+
+    /// Transform the text read from the reader line by line, like `crypto caesar`.
+    /// The line breaks are kept out of the transformation, so the rail fence doesn't move them.
+    fn transform_lines(&self, classic: &Classic, mut reader: impl BufRead) -> std::io::Result<()> {
+        let mut stdout = stdout().lock();
+        let mut line = String::new();
+
+        while reader.read_line(&mut line)? > 0 {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let line_break = &line[text.len()..];
+
+            stdout.write_all(classic.transform(&self.cipher, text).as_bytes())?;
+            stdout.write_all(line_break.as_bytes())?;
+            line.clear();
+        }
+
+        stdout.flush()
+    }
+}
+
+impl Classic {
+    /// The Vigenère cipher with the given key, ignoring its characters that are not letters.
+    pub fn vigenere(key: &str) -> Result<Self, String> {
+        let shifts: Vec<u8> = deunicode(key)
+            .chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_lowercase() as u8 - b'a')
+            .collect();
+
+        if shifts.is_empty() {
+            return Err("The key must contain at least one letter.".to_string());
+        }
+
+        Ok(Classic::Vigenere(shifts))
+    }
+
+    /// The affine cipher, the multiplier must be coprime with 26 to be reversible.
+    pub fn affine(multiplier: i64, shift: i64) -> Result<Self, String> {
+        #[allow(clippy::cast_possible_truncation)]
+        // rem_euclid returns a value in the range of 0..26
+        let (multiplier, shift) = (multiplier.rem_euclid(26) as u8, shift.rem_euclid(26) as u8);
+
+        if multiplier % 2 == 0 || multiplier == 13 {
+            return Err(format!(
+                "The multiplier {multiplier} is not coprime with 26, the message couldn't be decrypted."
+            ));
+        }
+
+        Ok(Classic::Affine { multiplier, shift })
+    }
+
+    fn transform(&self, cipher: &Cipher, value: &str) -> String {
+        match cipher {
+            Cipher::Encrypt => self.encrypt(value),
+            Cipher::Decrypt => self.decrypt(value),
+        }
+    }
+
+    pub fn encrypt(&self, value: &str) -> String {
+        let value = deunicode(value);
+
+        match self {
+            Classic::Vigenere(shifts) => vigenere(&value, shifts.iter().copied()),
+            Classic::Atbash => value.chars().map(atbash).collect(),
+            Classic::Rot13 => value.chars().map(|c| shift_letter(c, 13)).collect(),
+            Classic::Rot47 => value.chars().map(rot47).collect(),
+            Classic::Affine { multiplier, shift } => value
+                .chars()
+                .map(|c| map_letter(c, |x| affine(*multiplier, x, *shift)))
+                .collect(),
+            Classic::RailFence(rails) => rail_fence_encrypt(&value, *rails),
+        }
+    }
+
+    pub fn decrypt(&self, value: &str) -> String {
+        let value = deunicode(value);
+
+        match self {
+            Classic::Vigenere(shifts) => {
+                vigenere(&value, shifts.iter().map(|shift| (26 - shift) % 26))
+            }
+            Classic::Affine { multiplier, shift } => {
+                let inverse = (1..26)
+                    .find(|&inverse| affine(*multiplier, inverse, 0) == 1)
+                    .unwrap_or(1);
+
+                value
+                    .chars()
+                    .map(|c| map_letter(c, |y| affine(inverse, y + 26 - shift, 0)))
+                    .collect()
+            }
+            Classic::RailFence(rails) => rail_fence_decrypt(&value, *rails),
+            // The other ciphers are their own inverse
+            _ => self.encrypt(&value),
+        }
+    }
+}
+
+/// Shift each letter by the next shift of the key, the other characters don't use the key.
+fn vigenere(value: &str, shifts: impl Iterator<Item = u8> + Clone) -> String {
+    let mut key = shifts.cycle();
+
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                shift_letter(c, key.next().unwrap_or(0))
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// `(multiplier * x + shift) mod 26`, computed without overflow.
+fn affine(multiplier: u8, x: u8, shift: u8) -> u8 {
+    #[allow(clippy::cast_possible_truncation)]
+    // The result is in the range of 0..26
+    let result = ((u16::from(multiplier) * u16::from(x) + u16::from(shift)) % 26) as u8;
+
+    result
+}
+
+fn atbash(c: char) -> char {
+    map_letter(c, |x| 25 - x)
+}
+
+fn rot47(c: char) -> char {
+    match c {
+        '!'..='~' => (b'!' + (c as u8 - b'!' + 47) % 94) as char,
+        _ => c,
+    }
+}
+
+/// The rail of each position of a text of `length` characters, going down then up the rails.
+fn rail_pattern(length: usize, rails: usize) -> Vec<usize> {
+    let cycle = 2 * (rails - 1);
+
+    (0..length)
+        .map(|i| {
+            let position = i % cycle;
+            position.min(cycle - position)
+        })
+        .collect()
+}
+
+fn rail_fence_encrypt(value: &str, rails: usize) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let pattern = rail_pattern(chars.len(), rails);
+
+    (0..rails)
+        .flat_map(|rail| {
+            chars
+                .iter()
+                .zip(&pattern)
+                .filter(move |(_, r)| **r == rail)
+                .map(|(c, _)| *c)
+        })
+        .collect()
+}
+
+fn rail_fence_decrypt(value: &str, rails: usize) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let pattern = rail_pattern(chars.len(), rails);
+
+    // The positions of the text, in the order they were written in the encrypted text
+    let mut positions: Vec<usize> = (0..chars.len()).collect();
+    positions.sort_by_key(|&i| pattern[i]);
+
+    let mut result = vec![' '; chars.len()];
+
+    for (c, position) in chars.into_iter().zip(positions) {
+        result[position] = c;
+    }
+
+    result.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Classic::vigenere("LEMON").unwrap(), "ATTACK AT DAWN", "LXFOPV EF RNHR")]
+    #[case(Classic::vigenere("key").unwrap(), "Hello, World!", "Rijvs, Uyvjn!")]
+    #[case(Classic::vigenere("clé").unwrap(), "Élodie", "Gwsfti")]
+    #[case(Classic::Atbash, "Hello, World!", "Svool, Dliow!")]
+    #[case(Classic::Rot13, "Hello, World!", "Uryyb, Jbeyq!")]
+    #[case(Classic::Rot47, "Hello, World!", "w6==@[ (@C=5P")]
+    #[case(Classic::affine(5, 8).unwrap(), "Affine Cipher", "Ihhwvc Swfrcp")]
+    #[case(Classic::affine(1, 3).unwrap(), "Hello, World!", "Khoor, Zruog!")]
+    #[case(
+        Classic::RailFence(3),
+        "WEAREDISCOVEREDFLEEATONCE",
+        "WECRLTEERDSOEEFEAOCAIVDEN"
+    )]
+    #[case(Classic::RailFence(2), "Hello", "Hloel")]
+    fn test_encrypt(#[case] classic: Classic, #[case] value: &str, #[case] expected: &str) {
+        assert_eq!(classic.encrypt(value), expected);
+        assert_eq!(classic.decrypt(expected), deunicode(value));
+    }
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(subcommand)]
+        command: ClassicCmd,
+    }
+
+    #[rstest]
+    #[case(&["--crack"], None)]
+    #[case(&["--key", "key"], Some(Ok(Classic::vigenere("key").unwrap())))]
+    #[case(&[], Some(Err(())))]
+    #[case(&["--key", "key", "--crack"], Some(Err(())))]
+    fn test_vigenere_crack_flag(
+        #[case] args: &[&str],
+        #[case] expected: Option<Result<Classic, ()>>,
+    ) {
+        let cli = Cli::try_parse_from(
+            ["classic", "vigenere", "decrypt", "Rijvs"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+
+        let ClassicCmd::Vigenere(command) = cli.command else {
+            unreachable!();
+        };
+
+        assert_eq!(
+            command.classic().map(|classic| classic.map_err(|_| ())),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("123 !")]
+    fn test_invalid_vigenere_key(#[case] key: &str) {
+        assert!(Classic::vigenere(key).is_err());
+    }
+
+    #[rstest]
+    #[case(2)]
+    #[case(13)]
+    #[case(26)]
+    fn test_invalid_affine_multiplier(#[case] multiplier: i64) {
+        assert!(Classic::affine(multiplier, 0).is_err());
+    }
+
+    #[rstest]
+    #[case(2, "a")]
+    #[case(4, "ab")]
+    #[case(5, "The quick brown fox")]
+    fn test_rail_fence_round_trip(#[case] rails: usize, #[case] value: &str) {
+        let classic = Classic::RailFence(rails);
+
+        assert_eq!(classic.decrypt(&classic.encrypt(value)), value);
+    }
+}
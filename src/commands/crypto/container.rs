@@ -0,0 +1,594 @@
+//! The container format of the files encrypted by `crypto encrypt`.
+//!
+//! All the integers are big-endian.
+//!
+//! | Size      | Field                                                            |
+//! |-----------|------------------------------------------------------------------|
+//! | 8         | Magic bytes `CLIXYENC`                                           |
+//! | 1         | Version of the format, currently `1`                             |
+//! | 1         | Algorithm: `1` AES-256-GCM, `2` ChaCha20-Poly1305                |
+//! | 1         | Key derivation: `0` raw key file, `1` Argon2id                   |
+//! | 12        | Argon2id only: memory cost (KiB), iterations, parallelism        |
+//! | 16        | Random salt                                                      |
+//! | 4         | Size of the plaintext chunks                                     |
+//! | 7         | Random nonce prefix                                              |
+//!
+//! The header is followed by the chunks. Each chunk is the encryption of `chunk size` bytes
+//! of plaintext (less for the last one) followed by its 16 bytes tag. The nonce of a chunk is
+//! the nonce prefix, the index of the chunk on 4 bytes and a last chunk flag on 1 byte, so the
+//! chunks can't be reordered, dropped or truncated without being detected. The whole header is
+//! authenticated as the associated data of every chunk.
+//!
+//! The key of the chunks is unique to each file: with a password, it is derived by Argon2id from
+//! the password and the salt; with a key file, it is derived by HKDF-SHA256 from the raw key and
+//! the salt. A key file can then encrypt many files without risking a nonce reuse.
+
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::ChaCha20Poly1305;
+use clap::ValueEnum;
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha2::Sha256;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use super::input::read_full;
+
+const MAGIC: &[u8; 8] = b"CLIXYENC";
+const VERSION: u8 = 1;
+
+/// The size of the keys of both algorithms, in bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// The default size of the plaintext chunks.
+pub const CHUNK_SIZE: u32 = 64 * 1024;
+
+/// The largest chunk size accepted when decrypting, so a forged header can't exhaust the memory.
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The largest Argon2id memory cost accepted when decrypting (2 GiB).
+const MAX_MEMORY_COST: u32 = 2 * 1024 * 1024;
+
+/// The largest Argon2id number of iterations accepted when decrypting.
+const MAX_ITERATIONS: u32 = 32;
+
+/// The largest Argon2id degree of parallelism accepted when decrypting.
+const MAX_PARALLELISM: u32 = 64;
+
+/// The HKDF info of the key of the chunks, when it is derived from a key file.
+const HKDF_INFO: &[u8] = b"clixy encrypt chunk key";
+
+const SALT_SIZE: usize = 16;
+const NONCE_PREFIX_SIZE: usize = 7;
+const TAG_SIZE: usize = 16;
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum AeadAlgorithm {
+    #[default]
+    #[value(name = "aes-256-gcm")]
+    Aes256Gcm,
+    #[value(name = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    fn id(self) -> u8 {
+        match self {
+            AeadAlgorithm::Aes256Gcm => 1,
+            AeadAlgorithm::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(AeadAlgorithm::Aes256Gcm),
+            2 => Ok(AeadAlgorithm::ChaCha20Poly1305),
+            _ => Err(invalid_data("Unknown encryption algorithm")),
+        }
+    }
+}
+
+/// Where the key comes from.
+pub enum KeySource {
+    /// A raw key, read from a key file
+    Raw([u8; KEY_SIZE]),
+
+    /// A password, the key is derived from it with Argon2id
+    Password(Vec<u8>),
+}
+
+/// The parameters of the Argon2id key derivation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    /// The memory cost, in KiB
+    pub memory_cost: u32,
+
+    /// The number of iterations
+    pub iterations: u32,
+
+    /// The degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_cost: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// The header of an encrypted stream.
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+    algorithm: AeadAlgorithm,
+    kdf: Option<KdfParams>,
+    salt: [u8; SALT_SIZE],
+    chunk_size: u32,
+    nonce_prefix: [u8; NONCE_PREFIX_SIZE],
+}
+
+impl Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.algorithm.id());
+
+        match &self.kdf {
+            None => bytes.push(0),
+            Some(params) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&params.memory_cost.to_be_bytes());
+                bytes.extend_from_slice(&params.iterations.to_be_bytes());
+                bytes.extend_from_slice(&params.parallelism.to_be_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.chunk_size.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce_prefix);
+
+        bytes
+    }
+
+    /// Read the header, returning it with its bytes (the associated data of the chunks).
+    fn read(reader: &mut impl Read) -> Result<(Self, Vec<u8>)> {
+        let mut bytes = vec![0; MAGIC.len() + 3];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|_| invalid_data("The input is not a clixy encrypted file"))?;
+
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("The input is not a clixy encrypted file"));
+        }
+
+        if bytes[MAGIC.len()] != VERSION {
+            return Err(invalid_data(
+                "Unsupported version of the encrypted file format",
+            ));
+        }
+
+        let algorithm = AeadAlgorithm::from_id(bytes[MAGIC.len() + 1])?;
+
+        let kdf = match bytes[MAGIC.len() + 2] {
+            0 => None,
+            1 => {
+                let memory_cost = read_u32(reader, &mut bytes)?;
+                let iterations = read_u32(reader, &mut bytes)?;
+                let parallelism = read_u32(reader, &mut bytes)?;
+
+                // Checked before deriving the key, so a forged header can't make it run for hours
+                if memory_cost > MAX_MEMORY_COST
+                    || iterations > MAX_ITERATIONS
+                    || parallelism > MAX_PARALLELISM
+                {
+                    return Err(invalid_data("The key derivation parameters are too large"));
+                }
+
+                Some(KdfParams {
+                    memory_cost,
+                    iterations,
+                    parallelism,
+                })
+            }
+            _ => return Err(invalid_data("Unknown key derivation")),
+        };
+
+        let mut salt = [0; SALT_SIZE];
+        reader.read_exact(&mut salt)?;
+        bytes.extend_from_slice(&salt);
+
+        let chunk_size = read_u32(reader, &mut bytes)?;
+
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(invalid_data("Invalid chunk size"));
+        }
+
+        let mut nonce_prefix = [0; NONCE_PREFIX_SIZE];
+        reader.read_exact(&mut nonce_prefix)?;
+        bytes.extend_from_slice(&nonce_prefix);
+
+        Ok((
+            Header {
+                algorithm,
+                kdf,
+                salt,
+                chunk_size,
+                nonce_prefix,
+            },
+            bytes,
+        ))
+    }
+
+    fn nonce(&self, index: u32, last: bool) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(&self.nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..11].copy_from_slice(&index.to_be_bytes());
+        nonce[11] = u8::from(last);
+        nonce
+    }
+}
+
+/// The cipher of the chunks, with the key set.
+enum ChunkCipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl ChunkCipher {
+    fn new(algorithm: AeadAlgorithm, key: &[u8; KEY_SIZE]) -> Self {
+        match algorithm {
+            AeadAlgorithm::Aes256Gcm => {
+                ChunkCipher::Aes256Gcm(Box::new(Aes256Gcm::new(key.into())))
+            }
+            AeadAlgorithm::ChaCha20Poly1305 => {
+                ChunkCipher::ChaCha20Poly1305(Box::new(ChaCha20Poly1305::new(key.into())))
+            }
+        }
+    }
+
+    fn encrypt(&self, nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg, aad };
+
+        match self {
+            ChunkCipher::Aes256Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            ChunkCipher::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce.into(), payload),
+        }
+        .map_err(|_| Error::other("Error encrypting the data"))
+    }
+
+    fn decrypt(&self, nonce: &[u8; 12], msg: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let payload = Payload { msg, aad };
+
+        match self {
+            ChunkCipher::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            ChunkCipher::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload),
+        }
+        .map_err(|_| {
+            invalid_data("Decryption failed: wrong key or password, or the data was modified")
+        })
+    }
+}
+
+/// Encrypt everything read from the reader into the writer, chunk by chunk.
+pub fn encrypt(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    algorithm: AeadAlgorithm,
+    key_source: &KeySource,
+    kdf_params: KdfParams,
+    chunk_size: u32,
+) -> Result<()> {
+    let mut rng = rand::rng();
+
+    let mut nonce_prefix = [0; NONCE_PREFIX_SIZE];
+    rng.fill_bytes(&mut nonce_prefix);
+
+    let mut salt = [0; SALT_SIZE];
+    rng.fill_bytes(&mut salt);
+
+    let kdf = match key_source {
+        KeySource::Raw(_) => None,
+        KeySource::Password(_) => Some(kdf_params),
+    };
+
+    let header = Header {
+        algorithm,
+        kdf,
+        salt,
+        chunk_size,
+        nonce_prefix,
+    };
+
+    let aad = header.to_bytes();
+    let cipher = ChunkCipher::new(algorithm, &derive_key(key_source, &header)?);
+
+    writer.write_all(&aad)?;
+
+    let chunk_size = chunk_size as usize;
+    let mut chunk = vec![0; chunk_size];
+    let mut next_chunk = vec![0; chunk_size];
+
+    // The next chunk is read ahead to know if the current one is the last
+    let mut length = read_full(reader, &mut chunk)?;
+    let mut index: u32 = 0;
+
+    loop {
+        let next_length = if length < chunk_size {
+            0
+        } else {
+            read_full(reader, &mut next_chunk)?
+        };
+        let last = next_length == 0;
+
+        let encrypted = cipher.encrypt(&header.nonce(index, last), &chunk[..length], &aad)?;
+        writer.write_all(&encrypted)?;
+
+        if last {
+            break;
+        }
+
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| Error::other("The input is too large"))?;
+        std::mem::swap(&mut chunk, &mut next_chunk);
+        length = next_length;
+    }
+
+    writer.flush()
+}
+
+/// Decrypt everything read from the reader into the writer, chunk by chunk.
+/// `password` is called only if the data was encrypted with a password, to get it.
+/// Each chunk is authenticated before being written, but a modification in a later chunk is only
+/// detected after the previous ones are written, so the output must be discarded on error.
+pub fn decrypt(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    key_source: impl FnOnce(bool) -> Result<KeySource>,
+) -> Result<()> {
+    let (header, aad) = Header::read(reader)?;
+    let key_source = key_source(header.kdf.is_some())?;
+
+    if header.kdf.is_some() != matches!(key_source, KeySource::Password(_)) {
+        return Err(invalid_data(if header.kdf.is_some() {
+            "The data was encrypted with a password, not with a key file"
+        } else {
+            "The data was encrypted with a key file, not with a password"
+        }));
+    }
+
+    let cipher = ChunkCipher::new(header.algorithm, &derive_key(&key_source, &header)?);
+
+    let chunk_size = header.chunk_size as usize + TAG_SIZE;
+    let mut chunk = vec![0; chunk_size];
+    let mut next_chunk = vec![0; chunk_size];
+
+    let mut length = read_full(reader, &mut chunk)?;
+    let mut index: u32 = 0;
+
+    loop {
+        let next_length = if length < chunk_size {
+            0
+        } else {
+            read_full(reader, &mut next_chunk)?
+        };
+        let last = next_length == 0;
+
+        if length < TAG_SIZE {
+            return Err(invalid_data("The encrypted data is truncated"));
+        }
+
+        let decrypted = cipher.decrypt(&header.nonce(index, last), &chunk[..length], &aad)?;
+        writer.write_all(&decrypted)?;
+
+        if last {
+            break;
+        }
+
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| invalid_data("The encrypted data is too large"))?;
+        std::mem::swap(&mut chunk, &mut next_chunk);
+        length = next_length;
+    }
+
+    writer.flush()
+}
+
+/// Derive the key of the chunks from the key source and the salt of the header.
+fn derive_key(key_source: &KeySource, header: &Header) -> Result<[u8; KEY_SIZE]> {
+    let salt = &header.salt;
+
+    match (key_source, &header.kdf) {
+        (KeySource::Raw(key), _) => {
+            let mut derived = [0; KEY_SIZE];
+            Hkdf::<Sha256>::new(Some(salt), key)
+                .expand(HKDF_INFO, &mut derived)
+                .map_err(|e| Error::other(format!("Error deriving the key: {e}")))?;
+
+            Ok(derived)
+        }
+        (KeySource::Password(password), Some(params)) => {
+            let params = Params::new(
+                params.memory_cost,
+                params.iterations,
+                params.parallelism,
+                Some(KEY_SIZE),
+            )
+            .map_err(|e| invalid_data(&format!("Invalid key derivation parameters: {e}")))?;
+
+            let mut key = [0; KEY_SIZE];
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password, salt, &mut key)
+                .map_err(|e| Error::other(format!("Error deriving the key: {e}")))?;
+
+            Ok(key)
+        }
+        (KeySource::Password(_), None) => Err(invalid_data("Missing key derivation parameters")),
+    }
+}
+
+/// Read a big-endian u32, appending its bytes to the header bytes.
+fn read_u32(reader: &mut impl Read, bytes: &mut Vec<u8>) -> Result<u32> {
+    let mut value = [0; 4];
+    reader.read_exact(&mut value)?;
+    bytes.extend_from_slice(&value);
+
+    Ok(u32::from_be_bytes(value))
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// The KDF parameters written in the headers of the tests, far below `KdfParams::default()`
+    const TEST_KDF: KdfParams = KdfParams {
+        memory_cost: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    const TEST_CHUNK_SIZE: u32 = 16;
+
+    fn encrypt_bytes(
+        plaintext: &[u8],
+        algorithm: AeadAlgorithm,
+        key_source: &KeySource,
+    ) -> Vec<u8> {
+        let mut encrypted = vec![];
+
+        encrypt(
+            &mut &plaintext[..],
+            &mut encrypted,
+            algorithm,
+            key_source,
+            TEST_KDF,
+            TEST_CHUNK_SIZE,
+        )
+        .unwrap();
+
+        encrypted
+    }
+
+    fn decrypt_bytes(encrypted: &[u8], key_source: KeySource) -> Result<Vec<u8>> {
+        let mut decrypted = vec![];
+
+        decrypt(&mut &encrypted[..], &mut decrypted, |_| Ok(key_source))?;
+
+        Ok(decrypted)
+    }
+
+    #[rstest]
+    #[case(AeadAlgorithm::Aes256Gcm, 0)]
+    #[case(AeadAlgorithm::Aes256Gcm, 15)]
+    #[case(AeadAlgorithm::Aes256Gcm, 16)]
+    #[case(AeadAlgorithm::ChaCha20Poly1305, 32)]
+    #[case(AeadAlgorithm::ChaCha20Poly1305, 53)]
+    fn test_round_trip_key(#[case] algorithm: AeadAlgorithm, #[case] length: usize) {
+        let plaintext: Vec<u8> = (0..length).map(|i| i as u8).collect();
+
+        let encrypted = encrypt_bytes(&plaintext, algorithm, &KeySource::Raw([7; KEY_SIZE]));
+        let decrypted = decrypt_bytes(&encrypted, KeySource::Raw([7; KEY_SIZE])).unwrap();
+
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_round_trip_password() {
+        let plaintext = b"The quick brown fox jumps over the lazy dog";
+        let password = KeySource::Password(b"password".to_vec());
+
+        let encrypted = encrypt_bytes(plaintext, AeadAlgorithm::ChaCha20Poly1305, &password);
+
+        let decrypted = decrypt_bytes(&encrypted, KeySource::Password(b"password".to_vec()));
+        assert_eq!(decrypted.unwrap(), plaintext);
+
+        let wrong = decrypt_bytes(&encrypted, KeySource::Password(b"wrong".to_vec()));
+        assert_eq!(wrong.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tamper_detection() {
+        let plaintext = [42u8; 40];
+        let key = KeySource::Raw([1; KEY_SIZE]);
+        let encrypted = encrypt_bytes(&plaintext, AeadAlgorithm::Aes256Gcm, &key);
+        let header_size = Header::read(&mut &encrypted[..]).unwrap().1.len();
+
+        // A modified byte in a chunk
+        let mut modified = encrypted.clone();
+        modified[header_size + 3] ^= 1;
+        assert!(decrypt_bytes(&modified, KeySource::Raw([1; KEY_SIZE])).is_err());
+
+        // A modified header (the nonce prefix)
+        let mut modified = encrypted.clone();
+        modified[header_size - 1] ^= 1;
+        assert!(decrypt_bytes(&modified, KeySource::Raw([1; KEY_SIZE])).is_err());
+
+        // The last chunk dropped, the previous one isn't flagged as the last
+        let chunk = TEST_CHUNK_SIZE as usize + TAG_SIZE;
+        let truncated = &encrypted[..header_size + 2 * chunk];
+        assert!(decrypt_bytes(truncated, KeySource::Raw([1; KEY_SIZE])).is_err());
+
+        // Two chunks swapped
+        let mut swapped = encrypted[..header_size].to_vec();
+        swapped.extend_from_slice(&encrypted[header_size + chunk..header_size + 2 * chunk]);
+        swapped.extend_from_slice(&encrypted[header_size..header_size + chunk]);
+        swapped.extend_from_slice(&encrypted[header_size + 2 * chunk..]);
+        assert!(decrypt_bytes(&swapped, KeySource::Raw([1; KEY_SIZE])).is_err());
+
+        // The wrong key
+        assert!(decrypt_bytes(&encrypted, KeySource::Raw([2; KEY_SIZE])).is_err());
+    }
+
+    #[test]
+    fn test_key_file_subkey() {
+        let key = KeySource::Raw([3; KEY_SIZE]);
+        let encrypted = encrypt_bytes(b"data", AeadAlgorithm::Aes256Gcm, &key);
+        let (header, _) = Header::read(&mut &encrypted[..]).unwrap();
+
+        // The chunks aren't encrypted with the raw key itself
+        assert_ne!(derive_key(&key, &header).unwrap(), [3; KEY_SIZE]);
+    }
+
+    #[rstest]
+    #[case(MAX_MEMORY_COST + 1, 1, 1)]
+    #[case(64, MAX_ITERATIONS + 1, 1)]
+    #[case(64, 1, MAX_PARALLELISM + 1)]
+    fn test_kdf_params_too_large(
+        #[case] memory_cost: u32,
+        #[case] iterations: u32,
+        #[case] parallelism: u32,
+    ) {
+        let header = Header {
+            algorithm: AeadAlgorithm::Aes256Gcm,
+            kdf: Some(KdfParams {
+                memory_cost,
+                iterations,
+                parallelism,
+            }),
+            salt: [0; SALT_SIZE],
+            chunk_size: TEST_CHUNK_SIZE,
+            nonce_prefix: [0; NONCE_PREFIX_SIZE],
+        };
+
+        let error = Header::read(&mut &header.to_bytes()[..]).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let error = decrypt_bytes(b"not encrypted", KeySource::Raw([0; KEY_SIZE])).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
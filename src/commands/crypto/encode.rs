@@ -0,0 +1,657 @@
+use clap::{Args, ValueEnum, builder};
+use std::io::{BufRead, Error, ErrorKind, Read, Result, Write, stdout};
+
+use super::input::{InputCmdOpt, read_full};
+
+#[derive(Args, Clone)]
+pub struct Command {
+    #[arg(
+        value_enum,
+        action = clap::ArgAction::Set,
+        num_args = 1,
+        ignore_case = true,
+        help = "The encoding."
+    )]
+    encoding: Encoding,
+
+    #[arg(
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The string to encode or decode. Use - or omit it to read the raw bytes from stdin."
+    )]
+    value: Option<String>,
+
+    #[clap(flatten)]
+    input: InputCmdOpt,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    /// Standard base64 with padding (RFC 4648)
+    Base64,
+
+    /// URL-safe base64 with padding (RFC 4648)
+    Base64url,
+
+    /// Standard base64 without padding
+    Base64Nopad,
+
+    /// URL-safe base64 without padding
+    Base64urlNopad,
+
+    /// Base32 with padding (RFC 4648)
+    Base32,
+
+    /// Base58 with the Bitcoin alphabet, it can't be streamed so the input is limited to 16 KiB
+    Base58,
+
+    /// Hexadecimal, in lowercase when encoding
+    Hex,
+
+    /// Percent-encoding of the bytes out of the unreserved characters of URLs (RFC 3986)
+    Percent,
+
+    /// Quoted-printable (RFC 2045) with LF line breaks, the carriage returns of the input are escaped
+    QuotedPrintable,
+}
+
+/// The size of the buffer the input is encoded from.
+const ENCODE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The largest input of base58, whose conversion takes a time quadratic in the size of the input.
+const BASE58_MAX_SIZE: usize = 16 * 1024;
+
+/// The size of the lines of quoted-printable, without the soft line break.
+const QUOTED_PRINTABLE_LINE_LENGTH: usize = 75;
+
+impl Command {
+    pub fn encode(&self) {
+        self.execute(true);
+    }
+
+    pub fn decode(&self) {
+        self.execute(false);
+    }
+
+    fn execute(&self, encode: bool) {
+        let input = self.input.input(self.value.as_deref());
+
+        let mut reader = match input.reader() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Error reading the input: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        let mut stdout = stdout().lock();
+
+        let result = if encode {
+            self.encoding
+                .encode(&mut reader, &mut stdout)
+                // Like the other commands printing a text, the output ends with a line break
+                .and_then(|()| writeln!(stdout))
+        } else {
+            self.encoding.decode(&mut reader, &mut stdout)
+        };
+
+        if let Err(e) = result.and_then(|()| stdout.flush()) {
+            if e.kind() == ErrorKind::InvalidData {
+                eprintln!("{e}");
+            } else {
+                eprintln!("Error reading the input: {e}");
+            }
+
+            std::process::exit(1);
+        }
+    }
+}
+
+impl Encoding {
+    /// Encode everything read from the reader into the writer.
+    pub fn encode(&self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        if let Some((block, _)) = self.block() {
+            return self.encode_blocks(reader, writer, block);
+        }
+
+        match self {
+            Encoding::Base58 => {
+                let input = read_base58(reader)?;
+
+                writer.write_all(bs58::encode(input).into_string().as_bytes())
+            }
+            Encoding::Percent => encode_percent(reader, writer),
+            _ => encode_quoted_printable(reader, writer),
+        }
+    }
+
+    /// Decode everything read from the reader into the writer.
+    /// The whitespaces are ignored, except in percent-encoding and quoted-printable.
+    /// The decoded data is written as it is decoded, so it must be discarded on error.
+    pub fn decode(&self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        if let Some((_, block)) = self.block() {
+            return self.decode_blocks(reader, writer, block);
+        }
+
+        match self {
+            Encoding::Base58 => {
+                let mut input = read_base58(reader)?;
+                input.retain(|byte| !byte.is_ascii_whitespace());
+
+                let decoded = bs58::decode(input)
+                    .into_vec()
+                    .map_err(|e| invalid_input(*self, &e.to_string()))?;
+
+                writer.write_all(&decoded)
+            }
+            Encoding::Percent => decode_percent(reader, writer),
+            _ => decode_quoted_printable(reader, writer),
+        }
+    }
+
+    /// The size of the blocks of bytes encoded independently, and of their encoding.
+    /// `None` for the encodings that are not made of blocks.
+    fn block(&self) -> Option<(usize, usize)> {
+        match self {
+            Encoding::Base64
+            | Encoding::Base64url
+            | Encoding::Base64Nopad
+            | Encoding::Base64urlNopad => Some((3, 4)),
+            Encoding::Base32 => Some((5, 8)),
+            Encoding::Hex => Some((1, 2)),
+            Encoding::Base58 | Encoding::Percent | Encoding::QuotedPrintable => None,
+        }
+    }
+
+    fn data_encoding(&self) -> Option<data_encoding::Encoding> {
+        match self {
+            Encoding::Base64 => Some(data_encoding::BASE64),
+            Encoding::Base64url => Some(data_encoding::BASE64URL),
+            Encoding::Base64Nopad => Some(data_encoding::BASE64_NOPAD),
+            Encoding::Base64urlNopad => Some(data_encoding::BASE64URL_NOPAD),
+            Encoding::Base32 => Some(data_encoding::BASE32),
+            _ => None,
+        }
+    }
+
+    fn encode_block(&self, block: &[u8]) -> String {
+        match self.data_encoding() {
+            Some(encoding) => encoding.encode(block),
+            None => hex::encode(block),
+        }
+    }
+
+    fn encode_blocks(
+        &self,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+        block: usize,
+    ) -> Result<()> {
+        // The blocks are encoded independently, so the buffer must contain whole blocks
+        let mut buffer = vec![0; ENCODE_BUFFER_SIZE / block * block];
+
+        loop {
+            let length = read_full(reader, &mut buffer)?;
+            writer.write_all(self.encode_block(&buffer[..length]).as_bytes())?;
+
+            if length < buffer.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Decode the encodings made of blocks, a block at a time.
+    /// `offset` is the position of the block in the input, without the whitespaces.
+    fn decode_block(&self, block: &[u8], offset: usize) -> Result<Vec<u8>> {
+        match self.data_encoding() {
+            Some(encoding) => encoding.decode(block).map_err(|e| {
+                invalid_input(
+                    *self,
+                    &format!("{} at character {}", e.kind, offset + e.position + 1),
+                )
+            }),
+            None => hex::decode(block).map_err(|e| match e {
+                hex::FromHexError::InvalidHexCharacter { c, index } => invalid_input(
+                    *self,
+                    &format!(
+                        "invalid character {c:?} at character {}",
+                        offset + index + 1
+                    ),
+                ),
+                e => invalid_input(*self, &e.to_string()),
+            }),
+        }
+    }
+
+    fn decode_blocks(
+        &self,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+        block: usize,
+    ) -> Result<()> {
+        let mut pending: Vec<u8> = vec![];
+        let mut offset = 0;
+
+        // The padding ends the input, so it is checked apart from the blocks the input is read in
+        let padded = matches!(
+            self,
+            Encoding::Base64 | Encoding::Base64url | Encoding::Base32
+        );
+        let mut padding = false;
+
+        loop {
+            let buffer = reader.fill_buf()?;
+            let end = buffer.is_empty();
+
+            for &byte in buffer.iter().filter(|byte| !byte.is_ascii_whitespace()) {
+                if padded && byte == b'=' {
+                    padding = true;
+                } else if padding {
+                    return Err(invalid_input(
+                        *self,
+                        &format!(
+                            "data after the padding at character {}",
+                            offset + pending.len() + 1
+                        ),
+                    ));
+                }
+
+                pending.push(byte);
+            }
+
+            let length = buffer.len();
+            reader.consume(length);
+
+            // Only the complete blocks are decoded, until the end of the input
+            let complete = if end {
+                pending.len()
+            } else {
+                pending.len() / block * block
+            };
+
+            if complete > 0 {
+                writer.write_all(&self.decode_block(&pending[..complete], offset)?)?;
+                offset += complete;
+                pending.drain(..complete);
+            }
+
+            if end {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn invalid_input(encoding: Encoding, message: &str) -> Error {
+    let name = encoding
+        .to_possible_value()
+        .map_or(String::new(), |value| value.get_name().to_string());
+
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Invalid {name} input: {message}"),
+    )
+}
+
+/// Read the whole input of base58, failing if it is larger than `BASE58_MAX_SIZE`.
+fn read_base58(reader: &mut impl BufRead) -> Result<Vec<u8>> {
+    let mut input = vec![];
+    reader
+        .take(BASE58_MAX_SIZE as u64 + 1)
+        .read_to_end(&mut input)?;
+
+    if input.len() > BASE58_MAX_SIZE {
+        return Err(invalid_input(
+            Encoding::Base58,
+            &format!("larger than the limit of {} KiB", BASE58_MAX_SIZE / 1024),
+        ));
+    }
+
+    Ok(input)
+}
+
+/// Keep the unreserved characters of URLs, and encode the other bytes as `%XX`.
+fn percent_encode(byte: u8) -> String {
+    if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+        (byte as char).to_string()
+    } else {
+        format!("%{byte:02X}")
+    }
+}
+
+fn encode_percent(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+    let mut buffer = vec![0; ENCODE_BUFFER_SIZE];
+
+    loop {
+        let length = read_full(reader, &mut buffer)?;
+        let encoded: String = buffer[..length]
+            .iter()
+            .map(|&byte| percent_encode(byte))
+            .collect();
+        writer.write_all(encoded.as_bytes())?;
+
+        if length < buffer.len() {
+            return Ok(());
+        }
+    }
+}
+
+fn decode_percent(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+    let mut line = vec![];
+    let mut offset = 0;
+
+    // The encoded bytes don't contain line breaks, so the input can be decoded line by line
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let mut decoded = Vec::with_capacity(line.len());
+        let mut bytes = line.iter().enumerate();
+
+        while let Some((index, &byte)) = bytes.next() {
+            if byte != b'%' {
+                decoded.push(byte);
+                continue;
+            }
+
+            let high = bytes.next().map(|(_, byte)| *byte);
+            let low = bytes.next().map(|(_, byte)| *byte);
+
+            match (high, low) {
+                (Some(high), Some(low)) => match hex::decode([high, low]) {
+                    Ok(byte) => decoded.extend(byte),
+                    Err(_) => {
+                        return Err(invalid_input(
+                            Encoding::Percent,
+                            &format!("invalid escape at character {}", offset + index + 1),
+                        ));
+                    }
+                },
+                _ => {
+                    return Err(invalid_input(
+                        Encoding::Percent,
+                        &format!("incomplete escape at character {}", offset + index + 1),
+                    ));
+                }
+            }
+        }
+
+        writer.write_all(&decoded)?;
+        offset += line.len();
+        line.clear();
+    }
+
+    Ok(())
+}
+
+fn encode_quoted_printable(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+    let mut line = vec![];
+
+    // The lines are ended by LF, a carriage return is escaped like the other control characters
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let content = line.strip_suffix(b"\n").unwrap_or(&line);
+        let line_break = &line[content.len()..];
+
+        let mut encoded = String::new();
+        let mut length = 0;
+
+        for (index, &byte) in content.iter().enumerate() {
+            let last = index + 1 == content.len();
+
+            // The whitespaces at the end of a line would be removed by the transport
+            let literal = matches!(byte, b'!'..=b'<' | b'>'..=b'~')
+                || (matches!(byte, b' ' | b'\t') && !last);
+
+            let symbol = if literal {
+                (byte as char).to_string()
+            } else {
+                format!("={byte:02X}")
+            };
+
+            if length + symbol.len() > QUOTED_PRINTABLE_LINE_LENGTH {
+                encoded.push_str("=\n");
+                length = 0;
+            }
+
+            length += symbol.len();
+            encoded.push_str(&symbol);
+        }
+
+        writer.write_all(encoded.as_bytes())?;
+        writer.write_all(line_break)?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+fn decode_quoted_printable(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+    let mut line = vec![];
+    let mut number = 0;
+
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        number += 1;
+
+        let content = line.strip_suffix(b"\n").map_or(&line[..], |content| {
+            content.strip_suffix(b"\r").unwrap_or(content)
+        });
+        let mut line_break = &line[content.len()..];
+
+        // The trailing whitespaces were added by the transport
+        let mut content = content.trim_ascii_end();
+
+        // A soft line break is not a line break of the decoded data
+        if let Some(soft) = content.strip_suffix(b"=") {
+            content = soft;
+            line_break = b"";
+        }
+
+        let mut decoded = Vec::with_capacity(content.len());
+        let mut bytes = content.iter().enumerate();
+
+        while let Some((index, &byte)) = bytes.next() {
+            if byte != b'=' {
+                decoded.push(byte);
+                continue;
+            }
+
+            let escape = [bytes.next(), bytes.next()];
+
+            match escape {
+                [Some((_, &high)), Some((_, &low))] if hex::decode([high, low]).is_ok() => {
+                    decoded.extend(hex::decode([high, low]).unwrap_or_default());
+                }
+                _ => {
+                    return Err(invalid_input(
+                        Encoding::QuotedPrintable,
+                        &format!("invalid escape at line {number}, character {}", index + 1),
+                    ));
+                }
+            }
+        }
+
+        writer.write_all(&decoded)?;
+        writer.write_all(line_break)?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn encode(encoding: Encoding, input: &[u8]) -> String {
+        let mut output = vec![];
+        encoding.encode(&mut &input[..], &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    fn decode(encoding: Encoding, input: &str) -> Result<Vec<u8>> {
+        let mut output = vec![];
+        encoding.decode(&mut input.as_bytes(), &mut output)?;
+
+        Ok(output)
+    }
+
+    #[rstest]
+    #[case(Encoding::Base64, b"foobar?", "Zm9vYmFyPw==")]
+    #[case(Encoding::Base64url, b"\xfb\xff", "-_8=")]
+    #[case(Encoding::Base64Nopad, b"foob", "Zm9vYg")]
+    #[case(Encoding::Base64urlNopad, b"\xfb\xff", "-_8")]
+    #[case(Encoding::Base32, b"foobar", "MZXW6YTBOI======")]
+    #[case(Encoding::Base58, b"Hello World!", "2NEpo7TZRRrLZSi2U")]
+    #[case(Encoding::Base58, b"\0\0abc", "11ZiCa")]
+    #[case(Encoding::Hex, b"\x00\xffclixy", "00ff636c697879")]
+    #[case(Encoding::Percent, b"a b/c?d=\xc3\xa9~", "a%20b%2Fc%3Fd%3D%C3%A9~")]
+    #[case(
+        Encoding::QuotedPrintable,
+        b"caf\xc3\xa9 = 1 \nok\t",
+        "caf=C3=A9 =3D 1=20\nok=09"
+    )]
+    #[case(Encoding::Base64, b"", "")]
+    fn test_round_trip(#[case] encoding: Encoding, #[case] input: &[u8], #[case] expected: &str) {
+        assert_eq!(encode(encoding, input), expected);
+        assert_eq!(decode(encoding, expected).unwrap(), input);
+    }
+
+    #[rstest]
+    #[case(Encoding::Base64)]
+    #[case(Encoding::Base64Nopad)]
+    #[case(Encoding::Base32)]
+    #[case(Encoding::Hex)]
+    #[case(Encoding::QuotedPrintable)]
+    fn test_round_trip_large(#[case] encoding: Encoding) {
+        // Every byte value occurs, so the escapes of quoted-printable are split across the reads too
+        let input: Vec<u8> = (0..ENCODE_BUFFER_SIZE * 2 + 7)
+            .map(|i| (i * 7) as u8)
+            .collect();
+
+        assert_eq!(decode(encoding, &encode(encoding, &input)).unwrap(), input);
+    }
+
+    #[test]
+    fn test_base58_limit() {
+        let input = vec![1; BASE58_MAX_SIZE + 1];
+        let error = Encoding::Base58
+            .encode(&mut &input[..], &mut vec![])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[rstest]
+    #[case(Encoding::Base64, "Zm8=\nZm9v", Some(5))]
+    #[case(Encoding::Base64, "Zg==Zg==", Some(5))]
+    #[case(Encoding::Base32, "MY======MY======", Some(9))]
+    #[case(Encoding::Base64, "Zm9v\nZm8=", None)]
+    fn test_decode_padding_chunks(
+        #[case] encoding: Encoding,
+        #[case] input: &str,
+        #[case] position: Option<usize>,
+    ) {
+        // The same input is decoded whatever the size of the chunks it is read in
+        for capacity in 1..=input.len() {
+            let mut reader = std::io::BufReader::with_capacity(capacity, input.as_bytes());
+            let result = encoding.decode(&mut reader, &mut vec![]);
+
+            match position {
+                Some(position) => assert_eq!(
+                    result.unwrap_err().to_string(),
+                    format!(
+                        "Invalid {} input: data after the padding at character {position}",
+                        encoding.to_possible_value().unwrap().get_name()
+                    ),
+                    "{capacity}"
+                ),
+                None => assert!(result.is_ok(), "{capacity}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_quoted_printable_long_line() {
+        let input = "a".repeat(100);
+        let encoded = encode(Encoding::QuotedPrintable, input.as_bytes());
+
+        assert!(encoded.lines().all(|line| line.len() <= 76));
+        assert_eq!(
+            decode(Encoding::QuotedPrintable, &encoded).unwrap(),
+            input.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_quoted_printable_line_breaks() {
+        let input = format!("unix \nwindows\r\n{}\n\nend", "b".repeat(100));
+        let encoded = encode(Encoding::QuotedPrintable, input.as_bytes());
+
+        assert!(encoded.starts_with("unix=20\nwindows=0D\n"));
+        assert!(!encoded.contains('\r'));
+        assert_eq!(
+            decode(Encoding::QuotedPrintable, &encoded).unwrap(),
+            input.as_bytes()
+        );
+    }
+
+    #[rstest]
+    #[case(Encoding::Base64, "Zm9v\nYmFy\n", b"foobar")]
+    #[case(Encoding::Hex, "de ad\nbe ef", b"\xde\xad\xbe\xef")]
+    #[case(Encoding::Hex, "DEADBEEF", b"\xde\xad\xbe\xef")]
+    #[case(Encoding::QuotedPrintable, "soft=\r\nbreak  \r\n", b"softbreak\r\n")]
+    fn test_decode_lenient(
+        #[case] encoding: Encoding,
+        #[case] input: &str,
+        #[case] expected: &[u8],
+    ) {
+        assert_eq!(decode(encoding, input).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        Encoding::Base64,
+        "Zm9v!mFy",
+        "Invalid base64 input: invalid symbol at character 5"
+    )]
+    #[case(
+        Encoding::Base64,
+        "Zm9vY",
+        "Invalid base64 input: invalid length at character 5"
+    )]
+    #[case(
+        Encoding::Base32,
+        "MZXW6YT1",
+        "Invalid base32 input: invalid symbol at character 8"
+    )]
+    #[case(
+        Encoding::Base58,
+        "0OIl",
+        "Invalid base58 input: provided string contained invalid character '0' at byte 0"
+    )]
+    #[case(Encoding::Hex, "abc", "Invalid hex input: Odd number of digits")]
+    #[case(
+        Encoding::Hex,
+        "zz",
+        "Invalid hex input: invalid character 'z' at character 1"
+    )]
+    #[case(
+        Encoding::Percent,
+        "a%2",
+        "Invalid percent input: incomplete escape at character 2"
+    )]
+    #[case(
+        Encoding::Percent,
+        "%zz",
+        "Invalid percent input: invalid escape at character 1"
+    )]
+    #[case(
+        Encoding::QuotedPrintable,
+        "ok\n=4",
+        "Invalid quoted-printable input: invalid escape at line 2, character 1"
+    )]
+    fn test_decode_invalid(#[case] encoding: Encoding, #[case] input: &str, #[case] message: &str) {
+        let error = decode(encoding, input).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), message);
+    }
+}
@@ -0,0 +1,446 @@
+use std::fs::{self, File, OpenOptions, read};
+use std::io::{BufWriter, IsTerminal, Write, stdout};
+use std::path::PathBuf;
+
+use clap::{Args, builder};
+
+use super::Cipher;
+use super::container::{self, AeadAlgorithm, CHUNK_SIZE, KEY_SIZE, KdfParams, KeySource};
+use super::input::Input;
+use super::password::read_password;
+
+/// The arguments shared by the encryption and the decryption.
+#[derive(Args, Clone, Default)]
+pub struct ArgsEncryption {
+    #[arg(
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The file to read. Use - or omit it to read stdin."
+    )]
+    input: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The file to write, stdout if omitted. The file is only written once the whole input is processed."
+    )]
+    output: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Overwrite the output file if it exists."
+    )]
+    force: bool,
+
+    #[clap(flatten)]
+    key: ArgsEncryptionKey,
+}
+
+#[derive(Args, Clone)]
+pub struct EncryptCommand {
+    #[clap(flatten)]
+    args: ArgsEncryption,
+
+    #[arg(
+        short,
+        long,
+        default_value = "aes-256-gcm",
+        value_enum,
+        ignore_case = true,
+        help = "The encryption algorithm."
+    )]
+    algorithm: AeadAlgorithm,
+}
+
+/// The algorithm is read from the header of the input, so it isn't an argument.
+#[derive(Args, Clone)]
+pub struct DecryptCommand {
+    #[clap(flatten)]
+    args: ArgsEncryption,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "When decrypting without --output, stream the plaintext to stdout before the whole input is authenticated."
+    )]
+    allow_unauthenticated_stream: bool,
+}
+
+/// What the command does, with the arguments specific to it.
+enum Operation {
+    Encrypt(AeadAlgorithm),
+    Decrypt { allow_unauthenticated_stream: bool },
+}
+
+impl Operation {
+    fn cipher(&self) -> Cipher {
+        match self {
+            Operation::Encrypt(_) => Cipher::Encrypt,
+            Operation::Decrypt { .. } => Cipher::Decrypt,
+        }
+    }
+}
+
+/// The key of the encryption, from one of the possible sources.
+/// The password is asked interactively if none is given.
+#[derive(Args, Clone, Default)]
+#[group(multiple = false)]
+struct ArgsEncryptionKey {
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Use the key in the given file: 32 raw bytes, or 64 hexadecimal characters."
+    )]
+    key_file: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Derive the key from the password in the given file. A single trailing line break is ignored."
+    )]
+    password_file: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Derive the key from the password in the given environment variable."
+    )]
+    password_env: Option<String>,
+}
+
+impl ArgsEncryptionKey {
+    /// Returns the key from the selected source.
+    /// Without source, the password is asked, twice when encrypting so a typo doesn't lock the data.
+    fn read(&self, cipher: &Cipher) -> Result<KeySource, String> {
+        if let Some(key_file) = &self.key_file {
+            let key =
+                read(key_file).map_err(|e| format!("Error reading key file {key_file}: {e}"))?;
+
+            return parse_key(&key).map(KeySource::Raw).ok_or_else(|| {
+                format!(
+                    "The key file {key_file} must contain 32 bytes, or 64 hexadecimal characters"
+                )
+            });
+        }
+
+        let password = read_password(
+            self.password_file.as_deref(),
+            self.password_env.as_deref(),
+            *cipher == Cipher::Encrypt,
+        )?;
+
+        Ok(KeySource::Password(password))
+    }
+}
+
+impl EncryptCommand {
+    pub fn encrypt(&self) {
+        self.args.execute(&Operation::Encrypt(self.algorithm));
+    }
+}
+
+impl DecryptCommand {
+    pub fn decrypt(&self) {
+        self.args.execute(&Operation::Decrypt {
+            allow_unauthenticated_stream: self.allow_unauthenticated_stream,
+        });
+    }
+}
+
+impl ArgsEncryption {
+    fn execute(&self, operation: &Operation) {
+        if let Err(e) = self.run(operation) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+
+    fn run(&self, operation: &Operation) -> Result<(), String> {
+        let cipher = &operation.cipher();
+
+        match operation {
+            Operation::Encrypt(_) if self.output.is_none() && stdout().is_terminal() => {
+                return Err(
+                    "Refusing to write encrypted data to a terminal, use --output or a redirection"
+                        .to_string(),
+                );
+            }
+            // A modification is only detected after the previous chunks are written to stdout
+            Operation::Decrypt {
+                allow_unauthenticated_stream: false,
+            } if self.output.is_none() => {
+                return Err(
+                    "Refusing to write unauthenticated plaintext to stdout, use --output or --allow-unauthenticated-stream"
+                        .to_string(),
+                );
+            }
+            _ => {}
+        }
+
+        if let Some(output) = &self.output
+            && !self.force
+            && fs::exists(output).unwrap_or(true)
+        {
+            return Err(format!(
+                "The file {output} already exists, use --force to overwrite it"
+            ));
+        }
+
+        let input = match self.input.as_deref() {
+            None | Some("-") => Input::Stdin,
+            Some(path) => Input::File(PathBuf::from(path)),
+        };
+
+        let mut reader = input
+            .reader()
+            .map_err(|e| format!("Error reading the input: {e}"))?;
+
+        // The key is read before the input when encrypting, but when decrypting it depends on the header
+        let key_source = match cipher {
+            Cipher::Encrypt => Some(self.key.read(cipher)?),
+            Cipher::Decrypt => None,
+        };
+
+        let Some(output) = &self.output else {
+            let mut writer = BufWriter::new(stdout().lock());
+
+            return self
+                .process(operation, key_source, &mut reader, &mut writer)
+                .map_err(|e| format!("Error {}: {e}", action(cipher)));
+        };
+
+        // Written next to the output then renamed, so a failure never leaves a partial output
+        let part = format!("{output}.part");
+
+        let file = create_part(&part)
+            .map_err(|e| format!("Error creating the temporary output {part}: {e}"))?;
+
+        let mut writer = BufWriter::new(file);
+
+        let result = self
+            .process(operation, key_source, &mut reader, &mut writer)
+            .and_then(|()| writer.into_inner().map_err(|e| e.into_error()))
+            .and_then(|file| file.sync_all());
+
+        match result.and_then(|()| fs::rename(&part, output)) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = fs::remove_file(&part);
+                Err(format!("Error {}: {e}", action(cipher)))
+            }
+        }
+    }
+
+    fn process(
+        &self,
+        operation: &Operation,
+        key_source: Option<KeySource>,
+        reader: &mut impl std::io::Read,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        match (operation, key_source) {
+            (Operation::Encrypt(algorithm), Some(key_source)) => container::encrypt(
+                reader,
+                writer,
+                *algorithm,
+                &key_source,
+                KdfParams::default(),
+                CHUNK_SIZE,
+            ),
+            _ => container::decrypt(reader, writer, |password| {
+                // Don't ask for a password that can't be the key
+                if !password && self.key.key_file.is_none() {
+                    return Err(std::io::Error::other(
+                        "The data was encrypted with a key file, use --key-file",
+                    ));
+                }
+
+                self.key
+                    .read(&operation.cipher())
+                    .map_err(std::io::Error::other)
+            }),
+        }
+    }
+}
+
+fn action(cipher: &Cipher) -> &'static str {
+    match cipher {
+        Cipher::Encrypt => "encrypting",
+        Cipher::Decrypt => "decrypting",
+    }
+}
+
+/// Create the temporary output, readable by its owner only since it may hold a plaintext.
+/// It must not exist, so a file left by another run is never overwritten.
+fn create_part(path: &str) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)
+}
+
+/// Parse the content of a key file: the raw key, or the key in hexadecimal.
+fn parse_key(content: &[u8]) -> Option<[u8; KEY_SIZE]> {
+    if let Ok(key) = content.try_into() {
+        return Some(key);
+    }
+
+    let hex_key = std::str::from_utf8(content).ok()?.trim();
+    hex::decode(hex_key).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case(&[7; KEY_SIZE], Some([7; KEY_SIZE]))]
+    #[case(b"0707070707070707070707070707070707070707070707070707070707070707\n", Some([7; KEY_SIZE]))]
+    #[case(b"too short", None)]
+    #[case(b"07070707", None)]
+    #[case(
+        b"zz07070707070707070707070707070707070707070707070707070707070707",
+        None
+    )]
+    fn test_parse_key(#[case] content: &[u8], #[case] expected: Option<[u8; KEY_SIZE]>) {
+        assert_eq!(parse_key(content), expected);
+    }
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        command: DecryptCommand,
+    }
+
+    #[test]
+    fn test_decrypt_algorithm() {
+        // The algorithm is read from the header, so it can't be given when decrypting
+        assert!(
+            Cli::try_parse_from(["decrypt", "--key-file", "key", "-a", "aes-256-gcm"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["decrypt", "--key-file", "key"]).is_ok());
+    }
+
+    #[test]
+    fn test_existing_output() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        let key_file = dir.path().join("key");
+
+        fs::write(&input, b"secret").unwrap();
+        fs::write(&output, b"existing").unwrap();
+        fs::write(&key_file, [7; KEY_SIZE]).unwrap();
+
+        let mut command = ArgsEncryption {
+            input: Some(input.to_str().unwrap().to_string()),
+            output: Some(output.to_str().unwrap().to_string()),
+            key: ArgsEncryptionKey {
+                key_file: Some(key_file.to_str().unwrap().to_string()),
+                ..ArgsEncryptionKey::default()
+            },
+            ..ArgsEncryption::default()
+        };
+
+        assert!(
+            command
+                .run(&Operation::Encrypt(AeadAlgorithm::default()))
+                .is_err()
+        );
+        assert_eq!(fs::read(&output).unwrap(), b"existing");
+
+        command.force = true;
+
+        assert!(
+            command
+                .run(&Operation::Encrypt(AeadAlgorithm::default()))
+                .is_ok()
+        );
+        assert_ne!(fs::read(&output).unwrap(), b"existing");
+    }
+
+    #[test]
+    fn test_existing_part() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let output = dir.path().join("output");
+        let part = dir.path().join("output.part");
+        let key_file = dir.path().join("key");
+
+        fs::write(&input, b"secret").unwrap();
+        fs::write(&part, b"other run").unwrap();
+        fs::write(&key_file, [7; KEY_SIZE]).unwrap();
+
+        let command = ArgsEncryption {
+            input: Some(input.to_str().unwrap().to_string()),
+            output: Some(output.to_str().unwrap().to_string()),
+            key: ArgsEncryptionKey {
+                key_file: Some(key_file.to_str().unwrap().to_string()),
+                ..ArgsEncryptionKey::default()
+            },
+            ..ArgsEncryption::default()
+        };
+
+        assert!(
+            command
+                .run(&Operation::Encrypt(AeadAlgorithm::default()))
+                .is_err()
+        );
+        assert_eq!(fs::read(&part).unwrap(), b"other run");
+        assert!(!output.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("input");
+        let encrypted = dir.path().join("encrypted");
+        let decrypted = dir.path().join("decrypted");
+        let key_file = dir.path().join("key");
+
+        fs::write(&input, b"secret").unwrap();
+        fs::write(&key_file, [7; KEY_SIZE]).unwrap();
+
+        let encrypt = ArgsEncryption {
+            input: Some(input.to_str().unwrap().to_string()),
+            output: Some(encrypted.to_str().unwrap().to_string()),
+            key: ArgsEncryptionKey {
+                key_file: Some(key_file.to_str().unwrap().to_string()),
+                ..ArgsEncryptionKey::default()
+            },
+            ..ArgsEncryption::default()
+        };
+        encrypt
+            .run(&Operation::Encrypt(AeadAlgorithm::default()))
+            .unwrap();
+
+        ArgsEncryption {
+            input: encrypt.output.clone(),
+            output: Some(decrypted.to_str().unwrap().to_string()),
+            ..encrypt
+        }
+        .run(&Operation::Decrypt {
+            allow_unauthenticated_stream: false,
+        })
+        .unwrap();
+
+        assert_eq!(fs::read(&decrypted).unwrap(), b"secret");
+        assert_eq!(
+            fs::metadata(&decrypted).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+}
@@ -1,36 +1,185 @@
+use std::env;
+use std::fs::read;
+
 use clap::{Args, builder};
-use hex::encode;
 
-use crate::utils::hash::HashAlgorithm;
+use super::input::InputCmdOpt;
+use crate::utils::hash::{DigestEncoding, HashAlgorithm, update_hashers};
 
 #[derive(Args, Clone)]
 pub struct Command {
     #[arg(
-        required = true,
         value_parser = builder::NonEmptyStringValueParser::new(),
-        help = "The string to hash."
+        help = "The string to hash. Use - or omit it to hash the raw bytes read from stdin."
     )]
-    value: String,
+    value: Option<String>,
+
+    #[clap(flatten)]
+    input: InputCmdOpt,
 
     #[arg(
         short,
         long,
         default_value = "md5",
         value_enum,
-        action = clap::ArgAction::Set,
-        num_args = 1,
+        action = clap::ArgAction::Append,
+        num_args(1..),
+        value_delimiter = ',',
+        ignore_case = true,
+        help = "Specify the hash algorithms to use."
+    )]
+    algorithm: Vec<HashAlgorithm>,
+
+    #[arg(
+        short,
+        long,
+        default_value = "hex",
+        value_enum,
         ignore_case = true,
-        help = "Specify the hash algorithm to use."
+        help = "The encoding of the digests."
+    )]
+    encoding: DigestEncoding,
+
+    #[clap(flatten)]
+    key: ArgsHashKey,
+}
+
+/// The key of a keyed hash (HMAC, or keyed BLAKE3), from one of the possible sources.
+#[derive(Args, Clone)]
+#[group(multiple = false)]
+struct ArgsHashKey {
+    #[arg(
+        short,
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Compute a keyed hash (HMAC, or keyed BLAKE3) with the given key. \
+                Prefer --key-file or --key-env to keep the key out of the shell history."
+    )]
+    key: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Compute a keyed hash with the content of the given file as key. A single trailing line break is ignored."
+    )]
+    key_file: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Compute a keyed hash with the value of the given environment variable as key."
     )]
-    algorithm: HashAlgorithm,
+    key_env: Option<String>,
+}
+
+impl ArgsHashKey {
+    /// Returns the key from the selected source, `None` if no key was given.
+    fn read(&self) -> Result<Option<Vec<u8>>, String> {
+        if let Some(key) = &self.key {
+            return Ok(Some(key.as_bytes().to_vec()));
+        }
+
+        if let Some(key_file) = &self.key_file {
+            let mut key =
+                read(key_file).map_err(|e| format!("Error reading key file {key_file}: {e}"))?;
+
+            if key.ends_with(b"\n") {
+                key.pop();
+
+                if key.ends_with(b"\r") {
+                    key.pop();
+                }
+            }
+
+            return Ok(Some(key));
+        }
+
+        if let Some(key_env) = &self.key_env {
+            let key = env::var_os(key_env)
+                .ok_or_else(|| format!("The environment variable {key_env} is not set"))?;
+
+            return Ok(Some(key.into_encoded_bytes()));
+        }
+
+        Ok(None)
+    }
 }
 
 impl Command {
     pub fn execute(&self) {
-        println!("{}", self.algorithm);
+        let key = match self.key.read() {
+            Ok(key) => key,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
+
+        if key.as_ref().is_some_and(Vec::is_empty) {
+            eprintln!("The key is empty.");
+            return;
+        }
+
+        if let Some(algorithm) = self
+            .algorithm
+            .iter()
+            .find(|algorithm| !self.encoding.supports(algorithm))
+        {
+            eprintln!("The {algorithm} digests can't be printed with the sri encoding.");
+            return;
+        }
+
+        if key.is_some() && self.encoding == DigestEncoding::Sri {
+            eprintln!("The sri encoding can't be used with a keyed hash.");
+            return;
+        }
+
+        // Only a keyed hash authenticates the input, a plain digest is just printed.
+        // Printed on stderr, the digests stay the only output
+        if key.is_some() {
+            for algorithm in &self.algorithm {
+                algorithm.warn_if_insecure();
+            }
+        }
+
+        let mut hashers = vec![];
+
+        for algorithm in &self.algorithm {
+            let hasher = match &key {
+                Some(key) => algorithm.keyed_hasher(key),
+                None => Ok(algorithm.hasher()),
+            };
+
+            match hasher {
+                Ok(hasher) => hashers.push(hasher),
+                Err(e) => {
+                    eprintln!("{e}");
+                    return;
+                }
+            }
+        }
+
+        // The input is read once, whatever the number of algorithms
+        let input = self.input.input(self.value.as_deref());
+
+        if let Err(e) = input
+            .reader()
+            .and_then(|reader| update_hashers(&mut hashers, reader))
+        {
+            eprintln!("Error reading the input: {e}");
+            return;
+        }
+
+        for (algorithm, hasher) in self.algorithm.iter().zip(hashers) {
+            if key.is_some() {
+                println!("{}", algorithm.keyed_name());
+            } else {
+                println!("{algorithm}");
+            }
 
-        let hash = encode(self.algorithm.compute(self.value.as_bytes()));
+            let hash = self.encoding.encode(algorithm, &hasher.finalize());
 
-        println!("{hash}");
+            println!("{hash}");
+        }
     }
 }
@@ -0,0 +1,102 @@
+use clap::{Args, builder};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, ErrorKind, Read, Result, stdin};
+use std::path::PathBuf;
+
+/// The option to read the input of a crypto command from a file.
+/// The commands using it must have a positional `value` argument.
+#[derive(Args, Clone, Default)]
+pub struct InputCmdOpt {
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        conflicts_with = "value",
+        help = "Read the input from the given file instead of the value."
+    )]
+    input_file: Option<String>,
+}
+
+impl InputCmdOpt {
+    /// Returns where the input must be read from: the file if given, otherwise the value,
+    /// or stdin if the value is missing or is `-`.
+    pub fn input(&self, value: Option<&str>) -> Input {
+        match (&self.input_file, value) {
+            (Some(input_file), _) => Input::File(PathBuf::from(input_file)),
+            (None, None | Some("-")) => Input::Stdin,
+            (None, Some(value)) => Input::Value(value.to_string()),
+        }
+    }
+}
+
+/// The source of the input of a crypto command.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// The value given on the command line
+    Value(String),
+
+    /// The standard input, read as raw bytes
+    Stdin,
+
+    /// A file, read as raw bytes
+    File(PathBuf),
+}
+
+impl Input {
+    /// Returns a buffered reader over the input, so it can be streamed whatever its size.
+    pub fn reader(&self) -> Result<Box<dyn BufRead>> {
+        match self {
+            Input::Value(value) => Ok(Box::new(Cursor::new(value.clone().into_bytes()))),
+            Input::Stdin => Ok(Box::new(stdin().lock())),
+            Input::File(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+        }
+    }
+}
+
+/// Read until the buffer is full or the end of the reader, returning the number of bytes read.
+pub fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> Result<usize> {
+    let mut length = 0;
+
+    while length < buffer.len() {
+        match reader.read(&mut buffer[length..]) {
+            Ok(0) => break,
+            Ok(read) => length += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input() {
+        let opt = InputCmdOpt::default();
+
+        assert_eq!(opt.input(None), Input::Stdin);
+        assert_eq!(opt.input(Some("-")), Input::Stdin);
+        assert_eq!(opt.input(Some("abc")), Input::Value("abc".to_string()));
+
+        let opt = InputCmdOpt {
+            input_file: Some("file.txt".to_string()),
+        };
+
+        assert_eq!(opt.input(None), Input::File(PathBuf::from("file.txt")));
+    }
+
+    #[test]
+    fn test_value_reader() {
+        let mut content = String::new();
+
+        Input::Value("abc".to_string())
+            .reader()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert_eq!(content, "abc");
+    }
+}
@@ -1,7 +1,22 @@
 use clap::{Subcommand, ValueEnum};
 
+pub mod analysis;
 pub mod caesar;
+pub mod classic;
+pub mod container;
+pub mod encode;
+pub mod encrypt;
 pub mod hash;
+pub mod input;
+pub mod otp;
+pub mod password;
+pub mod signature;
+
+/// The exit code of the verify commands when the password, signature or code doesn't match.
+pub const EXIT_MISMATCH: i32 = 1;
+
+/// The exit code of the verify commands on an error, so scripts can tell it from a mismatch.
+pub const EXIT_ERROR: i32 = 2;
 
 #[derive(Subcommand, Clone)]
 #[command(about = "Cryptographic operations", visible_aliases = &["c"])]
@@ -9,8 +24,51 @@ pub enum CryptoCmd {
     #[command(about = "Hash the provided value", visible_aliases = &["h"])]
     Hash(hash::Command),
 
+    #[command(
+        about = "Encode the provided value in base64, base32, base58, hex, percent-encoding or quoted-printable"
+    )]
+    Encode(encode::Command),
+
+    #[command(
+        about = "Decode the provided value from base64, base32, base58, hex, percent-encoding or quoted-printable"
+    )]
+    Decode(encode::Command),
+
+    #[command(subcommand)]
+    Password(password::PasswordCmd),
+
+    #[command(subcommand)]
+    Otp(otp::OtpCmd),
+
+    #[command(about = "Generate an Ed25519 key pair")]
+    Keygen(signature::KeygenCommand),
+
+    #[command(about = "Sign a file with an Ed25519 private key, writing a detached signature")]
+    Sign(signature::SignCommand),
+
+    #[command(
+        about = "Verify the detached signature of a file with an Ed25519 public key",
+        after_help = "Exits with 0 if the signature is valid, 1 if it isn't and 2 on an error."
+    )]
+    Verify(signature::VerifyCommand),
+
     #[command(about = "Encrypt or decrypt a message using the Caesar cipher")]
     Caesar(caesar::Command),
+
+    #[command(subcommand)]
+    Classic(classic::ClassicCmd),
+
+    #[command(about = "Encrypt a file or stdin with AES-256-GCM or ChaCha20-Poly1305")]
+    Encrypt(encrypt::EncryptCommand),
+
+    #[command(
+        about = "Decrypt the output of the encrypt command, verifying it wasn't modified",
+        after_help = "Each chunk is authenticated before being written, but a modification of a later chunk is only \
+                      detected once the previous ones are written. With --output, nothing is written unless the whole \
+                      input is authenticated; writing to stdout requires --allow-unauthenticated-stream, \
+                      and the output must be discarded if the command exits with a non-zero status."
+    )]
+    Decrypt(encrypt::DecryptCommand),
 }
 
 #[derive(Debug, ValueEnum, Clone, PartialEq)]
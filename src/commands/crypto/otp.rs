@@ -0,0 +1,516 @@
+use std::env;
+use std::process::exit;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Args, Subcommand, ValueEnum, builder};
+
+use super::encode::Encoding;
+use super::{EXIT_ERROR, EXIT_MISMATCH};
+use crate::utils::hash::HashAlgorithm;
+
+#[derive(Subcommand, Clone)]
+#[command(about = "Generate or verify HOTP (RFC 4226) and TOTP (RFC 6238) one-time passwords")]
+pub enum OtpCmd {
+    #[command(about = "Generate the current one-time password")]
+    Generate(GenerateCommand),
+
+    #[command(
+        about = "Verify a one-time password",
+        after_help = "Exits with 0 if the code is valid, 1 if it isn't and 2 on an error."
+    )]
+    Verify(VerifyCommand),
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum OtpAlgorithm {
+    /// The default of most authenticator apps
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Args, Clone)]
+pub struct GenerateCommand {
+    #[clap(flatten)]
+    otp: ArgsOtp,
+}
+
+#[derive(Args, Clone)]
+pub struct VerifyCommand {
+    #[arg(help = "The code to verify.")]
+    code: String,
+
+    #[clap(flatten)]
+    otp: ArgsOtp,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        help = "The number of time steps accepted before and after the current one for TOTP, \
+                or of counters accepted after the given one for HOTP, to allow for clock drift."
+    )]
+    window: u64,
+}
+
+/// The parameters of the one-time passwords. The ones given explicitly override the ones of the URI.
+#[derive(Args, Clone)]
+struct ArgsOtp {
+    #[clap(flatten)]
+    secret: ArgsOtpSecret,
+
+    #[arg(
+        short,
+        long,
+        help = "Use HOTP with the given counter, instead of TOTP."
+    )]
+    counter: Option<u64>,
+
+    #[arg(
+        short,
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new().range(6..=10),
+        help = "The number of digits of the codes [default: 6]."
+    )]
+    digits: Option<u32>,
+
+    #[arg(
+        short,
+        long,
+        value_parser = builder::RangedU64ValueParser::<u64>::new().range(1..),
+        help = "The time step of TOTP, in seconds [default: 30]."
+    )]
+    period: Option<u64>,
+
+    #[arg(
+        short,
+        long,
+        value_enum,
+        ignore_case = true,
+        help = "The HMAC algorithm [default: sha1]."
+    )]
+    algorithm: Option<OtpAlgorithm>,
+
+    #[arg(
+        long,
+        help = "The time of TOTP, as a Unix timestamp, instead of the current time."
+    )]
+    time: Option<u64>,
+}
+
+/// The secret, from one of the possible sources.
+#[derive(Args, Clone, Default)]
+#[group(required = true, multiple = false)]
+struct ArgsOtpSecret {
+    #[arg(
+        short,
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "The secret, in base32. Prefer --secret-env or --uri-env to keep it out of the shell history."
+    )]
+    secret: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Read the secret in base32 from the given environment variable."
+    )]
+    secret_env: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Read the secret and the parameters from an otpauth:// URI, as encoded in the QR codes."
+    )]
+    uri: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Read the otpauth:// URI from the given environment variable."
+    )]
+    uri_env: Option<String>,
+}
+
+/// A one-time password generator.
+#[derive(Debug, PartialEq)]
+pub struct Otp {
+    pub secret: Vec<u8>,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+
+    /// The counter for HOTP, `None` for TOTP
+    pub counter: Option<u64>,
+
+    /// The time step of TOTP, in seconds
+    pub period: u64,
+}
+
+impl Default for Otp {
+    fn default() -> Self {
+        Otp {
+            secret: vec![],
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            counter: None,
+            period: 30,
+        }
+    }
+}
+
+impl GenerateCommand {
+    pub fn execute(&self) {
+        match self.otp.otp() {
+            Ok(otp) => match otp.code(otp.counter(self.otp.time())) {
+                Ok(code) => println!("{code}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    exit(EXIT_ERROR);
+                }
+            },
+            Err(e) => {
+                eprintln!("{e}");
+                exit(EXIT_ERROR);
+            }
+        }
+    }
+}
+
+impl VerifyCommand {
+    pub fn execute(&self) {
+        let otp = match self.otp.otp() {
+            Ok(otp) => otp,
+            Err(e) => {
+                eprintln!("{e}");
+                exit(EXIT_ERROR);
+            }
+        };
+
+        match otp.verify(self.code.trim(), self.otp.time(), self.window) {
+            // The next HOTP code must use the next counter
+            Ok(Some(counter)) if otp.counter.is_some() => {
+                println!("The code is valid, the next counter is {}.", counter + 1);
+            }
+            Ok(Some(_)) => println!("The code is valid."),
+            Ok(None) => {
+                println!("The code is not valid.");
+                exit(EXIT_MISMATCH);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                exit(EXIT_ERROR);
+            }
+        }
+    }
+}
+
+impl ArgsOtp {
+    fn otp(&self) -> Result<Otp, String> {
+        let uri = match (&self.secret.uri, &self.secret.uri_env) {
+            (Some(uri), _) => Some(uri.clone()),
+            (None, Some(uri_env)) => Some(read_env(uri_env)?),
+            (None, None) => None,
+        };
+
+        let mut otp = match (uri, &self.secret.secret, &self.secret.secret_env) {
+            (Some(uri), _, _) => Otp::from_uri(&uri)?,
+            (None, Some(secret), _) => Otp {
+                secret: decode_secret(secret)?,
+                ..Otp::default()
+            },
+            (None, None, Some(secret_env)) => Otp {
+                secret: decode_secret(&read_env(secret_env)?)?,
+                ..Otp::default()
+            },
+            (None, None, None) => return Err("The secret is missing".to_string()),
+        };
+
+        otp.counter = self.counter.or(otp.counter);
+        otp.digits = self.digits.unwrap_or(otp.digits);
+        otp.period = self.period.unwrap_or(otp.period);
+        otp.algorithm = self.algorithm.unwrap_or(otp.algorithm);
+
+        Ok(otp)
+    }
+
+    fn time(&self) -> u64 {
+        self.time.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        })
+    }
+}
+
+impl Otp {
+    /// Parse an `otpauth://totp/<label>?secret=<base32>&...` or `otpauth://hotp/...` URI.
+    pub fn from_uri(uri: &str) -> Result<Self, String> {
+        let invalid = |message: &str| format!("Invalid otpauth URI: {message}");
+
+        let rest = uri
+            .strip_prefix("otpauth://")
+            .ok_or_else(|| invalid("it must start with otpauth://"))?;
+        let (otp_type, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("the type is missing"))?;
+        let query = rest.split_once('?').map_or("", |(_, query)| query);
+
+        let mut otp = Otp::default();
+        let mut secret = None;
+        let mut counter = None;
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let value = percent_decode(value).map_err(|e| invalid(&e))?;
+
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(decode_secret(&value)?),
+                "algorithm" => {
+                    otp.algorithm = OtpAlgorithm::from_str(&value, true)
+                        .map_err(|_| invalid(&format!("unsupported algorithm {value}")))?;
+                }
+                "digits" => {
+                    otp.digits = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (6..=10).contains(digits))
+                        .ok_or_else(|| invalid(&format!("invalid digits {value}")))?;
+                }
+                "period" => {
+                    otp.period = value
+                        .parse()
+                        .ok()
+                        .filter(|period| *period > 0)
+                        .ok_or_else(|| invalid(&format!("invalid period {value}")))?;
+                }
+                "counter" => {
+                    counter = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(&format!("invalid counter {value}")))?,
+                    );
+                }
+                // The issuer and the unknown parameters only matter to the authenticator apps
+                _ => {}
+            }
+        }
+
+        otp.secret = secret.ok_or_else(|| invalid("the secret is missing"))?;
+
+        match otp_type.to_ascii_lowercase().as_str() {
+            "totp" => {}
+            "hotp" => otp.counter = Some(counter.ok_or_else(|| invalid("the counter is missing"))?),
+            _ => return Err(invalid(&format!("unknown type {otp_type}"))),
+        }
+
+        Ok(otp)
+    }
+
+    /// The HOTP counter, or the TOTP time step of the time.
+    fn counter(&self, time: u64) -> u64 {
+        self.counter.unwrap_or(time / self.period)
+    }
+
+    /// The HOTP code of the counter, which is the time step for TOTP (RFC 4226 section 5.3).
+    pub fn code(&self, counter: u64) -> Result<String, String> {
+        let algorithm = match self.algorithm {
+            OtpAlgorithm::Sha1 => HashAlgorithm::Sha1,
+            OtpAlgorithm::Sha256 => HashAlgorithm::Sha2_256,
+            OtpAlgorithm::Sha512 => HashAlgorithm::Sha2_512,
+        };
+
+        let mut hmac = algorithm.keyed_hasher(&self.secret)?;
+        hmac.update(&counter.to_be_bytes());
+        let hash = hmac.finalize();
+
+        // Dynamic truncation: 31 bits read at the offset given by the last 4 bits
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let value = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        let code = u64::from(value) % 10u64.pow(self.digits);
+
+        Ok(format!("{code:0width$}", width = self.digits as usize))
+    }
+
+    /// Verify the code, returning the counter (or time step) that matches it.
+    /// For TOTP, the time steps in the window before and after the current one are accepted.
+    /// For HOTP, the counters in the window after the given one are accepted.
+    pub fn verify(&self, code: &str, time: u64, window: u64) -> Result<Option<u64>, String> {
+        let counter = self.counter(time);
+
+        let counters = if self.counter.is_some() {
+            counter..=counter.saturating_add(window)
+        } else {
+            counter.saturating_sub(window)..=counter.saturating_add(window)
+        };
+
+        for counter in counters {
+            let expected = self.code(counter)?;
+
+            // Compare every byte, so the time doesn't tell how much of the code is right
+            if expected.len() == code.len()
+                && expected
+                    .bytes()
+                    .zip(code.bytes())
+                    .fold(0, |difference, (a, b)| difference | (a ^ b))
+                    == 0
+            {
+                return Ok(Some(counter));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Decode a base32 secret, ignoring the case, the spaces and the padding like the authenticator apps.
+fn decode_secret(secret: &str) -> Result<Vec<u8>, String> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let secret = data_encoding::BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| format!("Invalid base32 secret: {e}"))?;
+
+    if secret.is_empty() {
+        return Err("The secret is empty".to_string());
+    }
+
+    Ok(secret)
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let mut decoded = vec![];
+
+    Encoding::Percent
+        .decode(&mut value.as_bytes(), &mut decoded)
+        .map_err(|e| e.to_string())?;
+
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+fn read_env(name: &str) -> Result<String, String> {
+    env::var(name).map_err(|_| format!("The environment variable {name} is not set"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const SECRET_SHA1: &[u8] = b"12345678901234567890";
+    const SECRET_SHA256: &[u8] = b"12345678901234567890123456789012";
+    const SECRET_SHA512: &[u8] =
+        b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[rstest]
+    // RFC 4226 appendix D
+    #[case(0, "755224")]
+    #[case(1, "287082")]
+    #[case(2, "359152")]
+    #[case(5, "254676")]
+    #[case(9, "520489")]
+    fn test_hotp(#[case] counter: u64, #[case] expected: &str) {
+        let otp = Otp {
+            secret: SECRET_SHA1.to_vec(),
+            counter: Some(counter),
+            ..Otp::default()
+        };
+
+        assert_eq!(otp.code(otp.counter(0)), Ok(expected.to_string()));
+    }
+
+    #[rstest]
+    // RFC 6238 appendix B
+    #[case(59, OtpAlgorithm::Sha1, SECRET_SHA1, "94287082")]
+    #[case(59, OtpAlgorithm::Sha256, SECRET_SHA256, "46119246")]
+    #[case(59, OtpAlgorithm::Sha512, SECRET_SHA512, "90693936")]
+    #[case(1111111109, OtpAlgorithm::Sha1, SECRET_SHA1, "07081804")]
+    #[case(1111111109, OtpAlgorithm::Sha256, SECRET_SHA256, "68084774")]
+    #[case(20000000000, OtpAlgorithm::Sha512, SECRET_SHA512, "47863826")]
+    fn test_totp(
+        #[case] time: u64,
+        #[case] algorithm: OtpAlgorithm,
+        #[case] secret: &[u8],
+        #[case] expected: &str,
+    ) {
+        let otp = Otp {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: 8,
+            ..Otp::default()
+        };
+
+        assert_eq!(otp.code(otp.counter(time)), Ok(expected.to_string()));
+    }
+
+    #[test]
+    fn test_verify() {
+        let totp = Otp {
+            secret: SECRET_SHA1.to_vec(),
+            digits: 8,
+            ..Otp::default()
+        };
+
+        assert_eq!(totp.verify("94287082", 59, 0), Ok(Some(1)));
+        assert_eq!(totp.verify("94287082", 89, 1), Ok(Some(1)));
+        assert_eq!(totp.verify("94287082", 119, 1), Ok(None));
+        assert_eq!(totp.verify("9428708", 59, 1), Ok(None));
+
+        let hotp = Otp {
+            secret: SECRET_SHA1.to_vec(),
+            counter: Some(3),
+            ..Otp::default()
+        };
+
+        assert_eq!(hotp.verify("254676", 0, 2), Ok(Some(5)));
+        assert_eq!(hotp.verify("287082", 0, 2), Ok(None));
+    }
+
+    #[test]
+    fn test_from_uri() {
+        let otp = Otp::from_uri(
+            "otpauth://totp/ACME%20Co:john@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+
+        assert_eq!(
+            otp,
+            Otp {
+                secret: SECRET_SHA1.to_vec(),
+                algorithm: OtpAlgorithm::Sha256,
+                digits: 8,
+                counter: None,
+                period: 60,
+            }
+        );
+
+        let otp = Otp::from_uri("otpauth://hotp/label?secret=gezdgnbv gy3tqojq&counter=7").unwrap();
+
+        assert_eq!(otp.counter, Some(7));
+        assert_eq!(otp.secret, b"1234567890");
+    }
+
+    #[rstest]
+    #[case("https://example.com")]
+    #[case("otpauth://totp/label")]
+    #[case("otpauth://totp/label?secret=1")]
+    #[case("otpauth://hotp/label?secret=GEZDGNBV")]
+    #[case("otpauth://motp/label?secret=GEZDGNBV")]
+    #[case("otpauth://totp/label?secret=GEZDGNBV&digits=4")]
+    #[case("otpauth://totp/label?secret=GEZDGNBV&algorithm=MD5")]
+    fn test_invalid_uri(#[case] uri: &str) {
+        assert!(Otp::from_uri(uri).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use std::env;
+use std::fs::read;
+use std::process::exit;
+
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use clap::{Args, Subcommand, ValueEnum, builder};
+use rand::RngCore;
+use scrypt::Scrypt;
+
+use super::{EXIT_ERROR, EXIT_MISMATCH};
+
+#[derive(Subcommand, Clone)]
+#[command(about = "Hash passwords, or verify a password against a hash")]
+pub enum PasswordCmd {
+    #[command(about = "Hash a password, printing the PHC string to store")]
+    Hash(HashCommand),
+
+    #[command(
+        about = "Verify a password against a hash",
+        after_help = "Exits with 0 if the password matches, 1 if it doesn't and 2 on an error."
+    )]
+    Verify(VerifyCommand),
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum PasswordAlgorithm {
+    /// Memory-hard, the recommended choice
+    #[default]
+    Argon2id,
+
+    /// Printed in the usual `$2b$` format, the passwords are limited to 72 bytes
+    Bcrypt,
+
+    /// Memory-hard
+    Scrypt,
+}
+
+impl std::fmt::Display for PasswordAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordAlgorithm::Argon2id => write!(f, "argon2id"),
+            PasswordAlgorithm::Bcrypt => write!(f, "bcrypt"),
+            PasswordAlgorithm::Scrypt => write!(f, "scrypt"),
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct HashCommand {
+    #[arg(
+        short,
+        long,
+        default_value = "argon2id",
+        value_enum,
+        ignore_case = true,
+        help = "The password hashing algorithm."
+    )]
+    algorithm: PasswordAlgorithm,
+
+    #[clap(flatten)]
+    cost: ArgsPasswordCost,
+
+    #[clap(flatten)]
+    password: ArgsPassword,
+}
+
+#[derive(Args, Clone)]
+pub struct VerifyCommand {
+    #[arg(help = "The hash to verify the password against, as a PHC string or a bcrypt hash.")]
+    hash: String,
+
+    #[clap(flatten)]
+    password: ArgsPassword,
+}
+
+/// The cost parameters, each one only applies to some algorithms.
+/// The default values are the ones recommended by the libraries, the ranges are the ones they accept.
+#[derive(Args, Clone, Default)]
+struct ArgsPasswordCost {
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new().range(u64::from(argon2::Params::MIN_M_COST)..),
+        help = "Argon2id: the memory used, in KiB [default: 19456]."
+    )]
+    memory_cost: Option<u32>,
+
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new().range(u64::from(argon2::Params::MIN_T_COST)..),
+        help = "Argon2id: the number of iterations [default: 2]."
+    )]
+    iterations: Option<u32>,
+
+    // The Argon2 limit is below the scrypt one
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new()
+            .range(u64::from(argon2::Params::MIN_P_COST)..=u64::from(argon2::Params::MAX_P_COST)),
+        help = "Argon2id and scrypt: the degree of parallelism [default: 1]."
+    )]
+    parallelism: Option<u32>,
+
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new().range(4..=31),
+        help = "bcrypt: the cost, the number of rounds is 2^cost [default: 12]."
+    )]
+    cost: Option<u32>,
+
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u8>::new().range(1..64),
+        help = "scrypt: the CPU/memory cost, as the base 2 logarithm of N [default: 17]."
+    )]
+    log_n: Option<u8>,
+
+    // r * p must be below 2^30, which is checked with the parallelism when hashing
+    #[arg(
+        long,
+        value_parser = builder::RangedU64ValueParser::<u32>::new().range(1..0x4000_0000),
+        help = "scrypt: the block size r [default: 8]."
+    )]
+    block_size: Option<u32>,
+}
+
+/// Where to read the password from, it is asked interactively without echo by default.
+#[derive(Args, Clone)]
+#[group(multiple = false)]
+struct ArgsPassword {
+    #[arg(
+        long,
+        help = "Read the password from the given file. A single trailing line break is ignored."
+    )]
+    password_file: Option<String>,
+
+    #[arg(long, help = "Read the password from the given environment variable.")]
+    password_env: Option<String>,
+}
+
+impl PasswordAlgorithm {
+    /// Hash the password with a random salt.
+    fn hash(&self, password: &[u8], cost: &ArgsPasswordCost) -> Result<String, String> {
+        let unused = match self {
+            PasswordAlgorithm::Argon2id => {
+                cost.cost.is_some() || cost.log_n.is_some() || cost.block_size.is_some()
+            }
+            PasswordAlgorithm::Bcrypt => {
+                cost.memory_cost.is_some()
+                    || cost.iterations.is_some()
+                    || cost.parallelism.is_some()
+                    || cost.log_n.is_some()
+                    || cost.block_size.is_some()
+            }
+            PasswordAlgorithm::Scrypt => {
+                cost.memory_cost.is_some() || cost.iterations.is_some() || cost.cost.is_some()
+            }
+        };
+
+        if unused {
+            return Err(format!("Some of the cost parameters don't apply to {self}"));
+        }
+
+        let mut salt = [0; 16];
+        rand::rng().fill_bytes(&mut salt);
+
+        match self {
+            PasswordAlgorithm::Argon2id => {
+                let params = argon2::Params::new(
+                    cost.memory_cost.unwrap_or(argon2::Params::DEFAULT_M_COST),
+                    cost.iterations.unwrap_or(argon2::Params::DEFAULT_T_COST),
+                    cost.parallelism.unwrap_or(argon2::Params::DEFAULT_P_COST),
+                    None,
+                )
+                .map_err(|e| format!("Invalid cost parameters: {e}"))?;
+
+                let hasher =
+                    Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+                let salt = SaltString::encode_b64(&salt)
+                    .map_err(|e| format!("Error hashing the password: {e}"))?;
+
+                hasher
+                    .hash_password(password, &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| format!("Error hashing the password: {e}"))
+            }
+            PasswordAlgorithm::Bcrypt => {
+                let cost = cost.cost.unwrap_or(bcrypt::DEFAULT_COST);
+
+                // Refuse the long passwords instead of silently ignoring their end
+                bcrypt::non_truncating_hash_with_salt(password, cost, salt)
+                    .map(|parts| parts.format_for_version(bcrypt::Version::TwoB))
+                    .map_err(|e| format!("Error hashing the password: {e}"))
+            }
+            PasswordAlgorithm::Scrypt => {
+                let params = scrypt::Params::new(
+                    cost.log_n.unwrap_or(scrypt::Params::RECOMMENDED_LOG_N),
+                    cost.block_size.unwrap_or(scrypt::Params::RECOMMENDED_R),
+                    cost.parallelism.unwrap_or(scrypt::Params::RECOMMENDED_P),
+                    scrypt::Params::RECOMMENDED_LEN,
+                )
+                .map_err(|e| format!("Invalid cost parameters: {e}"))?;
+
+                let salt = SaltString::encode_b64(&salt)
+                    .map_err(|e| format!("Error hashing the password: {e}"))?;
+
+                Scrypt
+                    .hash_password_customized(password, None, None, params, &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| format!("Error hashing the password: {e}"))
+            }
+        }
+    }
+}
+
+/// Verify the password against a PHC string (Argon2, scrypt) or a bcrypt hash.
+/// Returns an error if the hash is invalid, to tell it from a wrong password.
+pub fn verify(password: &[u8], hash: &str) -> Result<bool, String> {
+    let hash = hash.trim();
+
+    if ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+    {
+        return bcrypt::verify(password, hash).map_err(|e| format!("Invalid bcrypt hash: {e}"));
+    }
+
+    let parsed = PasswordHash::new(hash).map_err(|e| format!("Invalid PHC string: {e}"))?;
+
+    let result = match parsed.algorithm.as_str() {
+        "argon2id" | "argon2i" | "argon2d" => Argon2::default().verify_password(password, &parsed),
+        "scrypt" => Scrypt.verify_password(password, &parsed),
+        algorithm => return Err(format!("Unsupported password hash algorithm: {algorithm}")),
+    };
+
+    match result {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(format!("Invalid hash: {e}")),
+    }
+}
+
+/// Read a password from a file, an environment variable, or ask it without echo.
+/// When it is asked, `confirm` asks it a second time so a typo doesn't go unnoticed.
+pub fn read_password(
+    password_file: Option<&str>,
+    password_env: Option<&str>,
+    confirm: bool,
+) -> Result<Vec<u8>, String> {
+    let password = if let Some(password_file) = password_file {
+        let mut password = read(password_file)
+            .map_err(|e| format!("Error reading password file {password_file}: {e}"))?;
+
+        if password.ends_with(b"\n") {
+            password.pop();
+
+            if password.ends_with(b"\r") {
+                password.pop();
+            }
+        }
+
+        password
+    } else if let Some(password_env) = password_env {
+        env::var_os(password_env)
+            .ok_or_else(|| format!("The environment variable {password_env} is not set"))?
+            .into_encoded_bytes()
+    } else {
+        let password = rpassword::prompt_password("Password: ")
+            .map_err(|e| format!("Error reading the password: {e}"))?;
+
+        if confirm {
+            let confirmation = rpassword::prompt_password("Confirm password: ")
+                .map_err(|e| format!("Error reading the password: {e}"))?;
+
+            if password != confirmation {
+                return Err("The passwords don't match".to_string());
+            }
+        }
+
+        password.into_bytes()
+    };
+
+    if password.is_empty() {
+        return Err("The password is empty".to_string());
+    }
+
+    Ok(password)
+}
+
+impl ArgsPassword {
+    fn read(&self, confirm: bool) -> Result<Vec<u8>, String> {
+        read_password(
+            self.password_file.as_deref(),
+            self.password_env.as_deref(),
+            confirm,
+        )
+    }
+}
+
+impl HashCommand {
+    pub fn execute(&self) {
+        let hash = self
+            .password
+            .read(true)
+            .and_then(|password| self.algorithm.hash(&password, &self.cost));
+
+        match hash {
+            Ok(hash) => println!("{hash}"),
+            Err(e) => {
+                eprintln!("{e}");
+                exit(EXIT_ERROR);
+            }
+        }
+    }
+}
+
+impl VerifyCommand {
+    pub fn execute(&self) {
+        let result = self
+            .password
+            .read(false)
+            .and_then(|password| verify(&password, &self.hash));
+
+        match result {
+            Ok(true) => println!("The password matches."),
+            Ok(false) => {
+                println!("The password doesn't match.");
+                exit(EXIT_MISMATCH);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                exit(EXIT_ERROR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rstest::rstest;
+
+    fn cost() -> ArgsPasswordCost {
+        ArgsPasswordCost::default()
+    }
+
+    #[rstest]
+    #[case(PasswordAlgorithm::Argon2id, ArgsPasswordCost { memory_cost: Some(64), iterations: Some(1), ..cost() }, "$argon2id$v=19$m=64,t=1,p=1$")]
+    #[case(PasswordAlgorithm::Bcrypt, ArgsPasswordCost { cost: Some(4), ..cost() }, "$2b$04$")]
+    #[case(PasswordAlgorithm::Scrypt, ArgsPasswordCost { log_n: Some(4), ..cost() }, "$scrypt$ln=4,r=8,p=1$")]
+    fn test_hash_verify(
+        #[case] algorithm: PasswordAlgorithm,
+        #[case] cost: ArgsPasswordCost,
+        #[case] prefix: &str,
+    ) {
+        let hash = algorithm.hash(b"password", &cost).unwrap();
+
+        assert!(hash.starts_with(prefix), "{hash}");
+        assert_eq!(verify(b"password", &hash), Ok(true));
+        assert_eq!(verify(b"wrong", &hash), Ok(false));
+    }
+
+    #[rstest]
+    // A test vector of the OpenBSD implementation
+    #[case(b"U*U", "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW")]
+    // Computed with Python's hashlib.scrypt
+    #[case(
+        b"hunter2",
+        "$scrypt$ln=4,r=8,p=1$c2FsdHNhbHQ$+5G/xu8hhej0zM71Fdp4uhawAtWRMUFOkLLIKsrC1EY"
+    )]
+    fn test_verify_known(#[case] password: &[u8], #[case] hash: &str) {
+        assert_eq!(verify(password, hash), Ok(true));
+    }
+
+    #[rstest]
+    #[case("not a hash")]
+    #[case("$md5$abc")]
+    #[case("$2b$04$short")]
+    fn test_verify_invalid(#[case] hash: &str) {
+        assert!(verify(b"password", hash).is_err());
+    }
+
+    #[test]
+    fn test_unused_cost() {
+        let cost = ArgsPasswordCost {
+            cost: Some(4),
+            ..cost()
+        };
+
+        assert!(
+            PasswordAlgorithm::Argon2id
+                .hash(b"password", &cost)
+                .is_err()
+        );
+    }
+
+    #[rstest]
+    #[case(&["--memory-cost", "8"], true)]
+    #[case(&["--memory-cost", "7"], false)]
+    #[case(&["--iterations", "0"], false)]
+    #[case(&["--parallelism", "16777215"], true)]
+    #[case(&["--parallelism", "16777216"], false)]
+    #[case(&["--cost", "31"], true)]
+    #[case(&["--cost", "3"], false)]
+    #[case(&["--cost", "32"], false)]
+    #[case(&["--log-n", "63"], true)]
+    #[case(&["--log-n", "64"], false)]
+    #[case(&["--block-size", "0"], false)]
+    fn test_cost_range(#[case] args: &[&str], #[case] valid: bool) {
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cost: ArgsPasswordCost,
+        }
+
+        let args = std::iter::once("clixy").chain(args.iter().copied());
+
+        assert_eq!(Cli::try_parse_from(args).is_ok(), valid);
+    }
+
+    #[test]
+    fn test_bcrypt_long_password() {
+        let cost = ArgsPasswordCost {
+            cost: Some(4),
+            ..cost()
+        };
+
+        assert!(PasswordAlgorithm::Bcrypt.hash(&[b'a'; 80], &cost).is_err());
+    }
+}
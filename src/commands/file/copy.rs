@@ -1,21 +1,62 @@
 use crate::commands::BaseCmdOpt;
-use crate::path_content::{IgnoreFlag, PathContent};
+use crate::commands::file::engine::{Verification, VerifyMode, copy_file};
+use crate::commands::file::journal::{self, CopyJournal, JournalEntry};
+use crate::commands::file::plan::{Plan, PlanAction};
+use crate::commands::file::preserve::{Preserve, PreserveAttribute};
+use crate::commands::file::remove::{plan_removals, remove_dirs, remove_files};
+use crate::commands::file::{DryRunCmdOpt, FilterCmdOpt};
+use crate::path_content::{IgnoreFlag, PathContent, SymlinkPolicy};
 use crate::progress_bar_helper;
 use crate::utils::hash::HashAlgorithm;
 use crate::utils::{add_error, confirm_continue, round_bytes_size};
 use clap::{Args, builder};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::fs::{File, copy, create_dir_all};
-use std::io::Read;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{create_dir_all, read_link, remove_file};
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum OptionsTypes {
     None,
     Replace,
     Complete,
     Update,
+    /// Skip the files with the same size and digest
+    Checksum(HashAlgorithm),
+    /// Skip the files with the same size
+    SizeOnly,
+}
+
+/// A file copied to the destination, waiting for its verification.
+pub struct CopiedFile {
+    /// The index of the file in the path content
+    pub index: usize,
+
+    /// The path of the source file
+    pub source: PathBuf,
+
+    /// The path of the destination file
+    pub destination: PathBuf,
+
+    /// The digest of the source bytes computed during the copy.
+    /// Not set when the files are not verified.
+    pub source_hash: Option<Vec<u8>>,
+}
+
+/// The destination entries to remove in mirror mode.
+pub struct MirrorEntries {
+    /// The path the destination entries were indexed from
+    pub root: PathBuf,
+
+    /// The entries whose type changed in the source (a file replaced by a folder for example),
+    /// with the content of the conflicting folders. They are removed before the copy.
+    pub conflicting: PathContent,
+
+    /// The entries that are not in the source. They are removed once the copy succeeded.
+    pub extraneous: PathContent,
 }
 
 #[derive(Args, Clone)]
@@ -25,7 +66,7 @@ struct ArgsCopyPossiblesOptions {
         long,
         default_value = "false",
         value_parser = builder::BoolValueParser::new(),
-        help = "Replace the destination path and its contents if they already exist. Cannot be used with --complete or --update."
+        help = "Replace the destination path and its contents if they already exist. Cannot be used with the other options."
     )]
     replace: bool,
 
@@ -33,7 +74,7 @@ struct ArgsCopyPossiblesOptions {
         long,
         default_value = "false",
         value_parser = builder::BoolValueParser::new(),
-        help = "Only copy files that do not exist in the destination folder. Cannot be used with --replace or --update."
+        help = "Only copy files that do not exist in the destination folder. Cannot be used with the other options."
     )]
     complete: bool,
 
@@ -41,9 +82,25 @@ struct ArgsCopyPossiblesOptions {
         long,
         default_value = "false",
         value_parser = builder::BoolValueParser::new(),
-        help = "Update destination files only if they are older than the source files. Cannot be used with --replace or --complete."
+        help = "Update destination files only if they are older than the source files. Cannot be used with the other options."
     )]
     update: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Skip destination files with the same size and digest (see --algorithm) as the source files. Cannot be used with the other options."
+    )]
+    checksum: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Skip destination files with the same size as the source files. Cannot be used with the other options."
+    )]
+    size_only: bool,
 }
 
 #[derive(Args, Clone)]
@@ -72,6 +129,22 @@ pub struct Command {
     #[clap(flatten)]
     options: ArgsCopyPossiblesOptions,
 
+    #[arg(
+        short,
+        long,
+        default_value = "sha2-256",
+        value_enum,
+        ignore_case = true,
+        help = "The hash algorithm used to verify the copied files and to compare the files with --checksum."
+    )]
+    algorithm: HashAlgorithm,
+
+    #[clap(flatten)]
+    filter: FilterCmdOpt,
+
+    #[clap(flatten)]
+    dry_run: DryRunCmdOpt,
+
     #[arg(
         long,
         default_value = "false",
@@ -95,6 +168,68 @@ pub struct Command {
         help = "Skip verification of files after copying them to the destination."
     )]
     no_verify: bool,
+
+    #[arg(
+        long,
+        default_value = "full",
+        value_enum,
+        ignore_case = true,
+        conflicts_with = "no_verify",
+        help = "How the copied files are verified: full re-reads the destination files, \
+                fast flushes them to the disk and trusts the write."
+    )]
+    verify: VerifyMode,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        conflicts_with = "dry_run",
+        help = "Resume an interrupted copy using the journal written next to the destination path. \
+                Without it, the journal of an interrupted copy is discarded and the copy starts over."
+    )]
+    resume: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        action = clap::ArgAction::Append,
+        num_args(1..),
+        value_delimiter = ',',
+        ignore_case = true,
+        help = "Preserve the given attributes of the copied files and folders: mode, timestamps, ownership or all. \
+                The files always keep their mode."
+    )]
+    preserve: Vec<PreserveAttribute>,
+
+    #[arg(
+        long,
+        default_value = "copy-link",
+        value_enum,
+        ignore_case = true,
+        help = "How to handle symbolic links: copy the links themselves, follow them or skip them."
+    )]
+    symlinks: SymlinkPolicy,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        conflicts_with = "only_folders",
+        help = "Make the destination an exact replica of the source by removing the destination entries that are not in the source. \
+                They are removed once the copy succeeded, and the entries excluded by the filters are kept."
+    )]
+    mirror: bool,
+
+    #[arg(
+        short,
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        requires = "mirror",
+        help = "Automatically confirms the removal of the destination entries that are not in the source without prompting for user confirmation."
+    )]
+    yes: bool,
 }
 
 pub fn execute(cmd: Command) {
@@ -107,19 +242,46 @@ pub fn execute(cmd: Command) {
                 replace,
                 complete,
                 update,
+                checksum,
+                size_only,
             },
+        algorithm,
+        filter,
+        dry_run: DryRunCmdOpt {
+            dry_run,
+            plan_format,
+        },
         copy_target,
         only_folders,
         no_verify,
+        verify,
+        resume,
+        preserve,
+        symlinks,
+        mirror,
+        yes,
     } = cmd;
 
-    let option = match (replace, complete, update) {
-        (true, false, false) => OptionsTypes::Replace,
-        (false, true, false) => OptionsTypes::Complete,
-        (false, false, true) => OptionsTypes::Update,
+    let preserve = Preserve::from_attributes(&preserve);
+
+    let option = match (replace, complete, update, checksum, size_only) {
+        (true, false, false, false, false) => OptionsTypes::Replace,
+        (false, true, false, false, false) => OptionsTypes::Complete,
+        (false, false, true, false, false) => OptionsTypes::Update,
+        (false, false, false, true, false) => OptionsTypes::Checksum(algorithm.clone()),
+        (false, false, false, false, true) => OptionsTypes::SizeOnly,
         _ => OptionsTypes::None,
     };
 
+    if !no_verify || checksum {
+        algorithm.warn_if_insecure();
+    }
+
+    let verification = (!no_verify).then_some(Verification {
+        algorithm,
+        mode: verify,
+    });
+
     if rayon::ThreadPoolBuilder::new()
         .num_threads(workers)
         .build_global()
@@ -139,7 +301,19 @@ pub fn execute(cmd: Command) {
     let source_path = Path::new(&source);
     let destination_path = Path::new(&destination);
 
-    let mut path_content = PathContent::new();
+    if mirror && !source_path.is_dir() {
+        eprintln!("The mirror mode requires a source folder, aborting copy");
+        return;
+    }
+
+    let Some(filter) = filter.to_filter() else {
+        println!("Aborting copy");
+        return;
+    };
+
+    let mut path_content = PathContent::new()
+        .with_symlinks(symlinks)
+        .with_filter(filter);
 
     let ignore_flag = if only_folders {
         IgnoreFlag::Files
@@ -160,7 +334,66 @@ pub fn execute(cmd: Command) {
         return;
     }
 
-    if destination_path.exists() && option == OptionsTypes::None {
+    // Nothing is written in dry-run mode, so the journal is left untouched
+    let journal_path = if dry_run {
+        None
+    } else {
+        CopyJournal::path_for(destination_path).ok()
+    };
+
+    // A copy is only resumed with the options it was started with
+    let fingerprint = journal::fingerprint(
+        &path_content,
+        &journal_options(&option, &preserve, verification.as_ref()),
+    );
+
+    let mut journal = None;
+
+    if resume {
+        let Some(journal_path) = &journal_path else {
+            eprintln!(
+                "Unable to determine the journal location for the destination path, nothing to resume"
+            );
+            return;
+        };
+
+        let Ok(fingerprint) = &fingerprint else {
+            eprintln!("Error computing the fingerprint of the source path, aborting copy");
+            return;
+        };
+
+        match CopyJournal::load(destination_path, fingerprint) {
+            Ok(loaded) => {
+                println!(
+                    "Resuming copy: {} directories created, {} files copied and {} files verified",
+                    loaded.created_dirs.len(),
+                    loaded.copied_files.len(),
+                    loaded.verified_files.len()
+                );
+                journal = Some(loaded);
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                eprintln!(
+                    "No journal found at {}, nothing to resume",
+                    journal_path.display()
+                );
+                return;
+            }
+            Err(e) => {
+                eprintln!("Unable to resume the copy: {e}, aborting copy");
+                return;
+            }
+        }
+    } else if let Some(journal_path) = &journal_path
+        && journal_path.exists()
+    {
+        eprintln!(
+            "A journal from an interrupted copy exists at {}, it will be discarded (use --resume to continue it instead)",
+            journal_path.display()
+        );
+    }
+
+    if destination_path.exists() && option == OptionsTypes::None && !resume && !mirror {
         let Ok(content) = destination_path.read_dir() else {
             eprintln!("Error reading destination folder content, check the path or permissions");
             return;
@@ -172,6 +405,8 @@ pub fn execute(cmd: Command) {
             );
             return;
         }
+    } else if dry_run {
+        // Nothing is created in dry-run mode
     } else if create_dir_all(destination_path).is_err() {
         eprintln!("Unable to create destination folder, check the path or permissions");
         return;
@@ -179,11 +414,92 @@ pub fn execute(cmd: Command) {
         println!("Destination path created");
     }
 
+    // In mirror mode, the destination entries that are not in the source
+    let mirror_entries = if mirror {
+        match mirror_extraneous(&path_content, source_path, destination_path, copy_target) {
+            Ok(mirror_entries) => Some(mirror_entries),
+            Err(e) => {
+                eprintln!("Error indexing destination path ({e}), aborting copy");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    if dry_run {
+        let mut plan = plan_copy(
+            &path_content,
+            source_path,
+            destination_path,
+            copy_target,
+            &option,
+        );
+
+        if let Some(mirror_entries) = &mirror_entries {
+            plan_removals(&mut plan, &mirror_entries.conflicting);
+            plan_removals(&mut plan, &mirror_entries.extraneous);
+        }
+
+        plan.print(plan_format);
+        return;
+    }
+
+    let list_of_errors = Arc::new(Mutex::new(vec![]));
+
+    if let Some(MirrorEntries {
+        root,
+        conflicting,
+        extraneous,
+    }) = &mirror_entries
+        && conflicting.entries + extraneous.entries > 0
+        && !yes
+    {
+        if conflicting.entries > 0 {
+            println!(
+                "Before the copy, {} files and {} directories will be removed from {} as their type changed in the source ({} entries, {})",
+                conflicting.list_of_files.len(),
+                conflicting.list_of_dirs.len(),
+                root.display(),
+                conflicting.entries,
+                round_bytes_size(conflicting.size)
+            );
+        }
+
+        if extraneous.entries > 0 {
+            println!(
+                "Once the copy succeeded, {} files and {} directories will be removed from {} as they are not in the source ({} entries, {})",
+                extraneous.list_of_files.len(),
+                extraneous.list_of_dirs.len(),
+                root.display(),
+                extraneous.entries,
+                round_bytes_size(extraneous.size)
+            );
+        }
+
+        if !confirm_continue() {
+            println!("Aborting copy");
+            return;
+        }
+    }
+
+    // Files already copied by an interrupted copy don't need more space
+    let needed_size = journal.as_ref().map_or(path_content.size, |journal| {
+        let copied_size: u64 = journal
+            .copied_files
+            .iter()
+            .filter_map(|index| path_content.list_of_files.get(*index)?.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        path_content.size.saturating_sub(copied_size)
+    });
+
     if let Ok(available_space) = fs4::available_space(destination_path) {
-        if available_space < path_content.size {
+        if available_space < needed_size {
             eprintln!(
                 "Not enough space available in the destination folder ({} needed, {} available), aborting copy",
-                round_bytes_size(path_content.size),
+                round_bytes_size(needed_size),
                 round_bytes_size(available_space)
             );
             return;
@@ -193,7 +509,34 @@ pub fn execute(cmd: Command) {
         return;
     }
 
-    let list_of_errors = Arc::new(Mutex::new(vec![]));
+    if journal_path.is_none() {
+        eprintln!(
+            "Unable to determine the journal location for the destination path, the copy will not be resumable"
+        );
+    } else if journal.is_none() {
+        match fingerprint
+            .and_then(|fingerprint| CopyJournal::create(destination_path, &fingerprint))
+        {
+            Ok(created) => journal = Some(created),
+            Err(e) => {
+                eprintln!("Unable to write the copy journal ({e}), the copy will not be resumable");
+            }
+        }
+    }
+
+    // The entries whose type changed are in the way of the copy, so they are removed first
+    if let Some(MirrorEntries {
+        root, conflicting, ..
+    }) = &mirror_entries
+        && conflicting.entries > 0
+    {
+        let files_ok =
+            conflicting.list_of_files.is_empty() || remove_files(conflicting, &list_of_errors);
+
+        if files_ok && !conflicting.list_of_dirs.is_empty() {
+            remove_dirs(conflicting, &list_of_errors, root);
+        }
+    }
 
     let dirs_ok;
 
@@ -207,6 +550,7 @@ pub fn execute(cmd: Command) {
             destination_path,
             &list_of_errors,
             copy_target,
+            journal.as_ref(),
         );
     }
 
@@ -218,15 +562,56 @@ pub fn execute(cmd: Command) {
             &list_of_errors,
             copy_target,
             &option,
+            &preserve,
+            verification.as_ref(),
+            journal.as_ref(),
         );
 
-        if !no_verify {
-            verify_copy(&copied_files, &list_of_errors);
+        if let Some(verification) = &verification {
+            verify_copy(
+                &copied_files,
+                verification,
+                &list_of_errors,
+                journal.as_ref(),
+            );
         }
     } else {
         println!("No files to copy or files were skipped");
     }
 
+    // The extraneous entries are only removed once the copy succeeded, like rsync --delete-after,
+    // and before the attributes of the directories are preserved since the removals change them
+    if let Some(MirrorEntries {
+        root, extraneous, ..
+    }) = &mirror_entries
+        && extraneous.entries > 0
+    {
+        if list_of_errors.lock().is_ok_and(|errors| errors.is_empty()) {
+            let files_ok =
+                extraneous.list_of_files.is_empty() || remove_files(extraneous, &list_of_errors);
+
+            if files_ok && !extraneous.list_of_dirs.is_empty() {
+                remove_dirs(extraneous, &list_of_errors, root);
+            }
+        } else {
+            eprintln!(
+                "The copy failed, the entries of {} that are not in the source were kept",
+                root.display()
+            );
+        }
+    }
+
+    if dirs_ok && !preserve.is_empty() && !path_content.list_of_dirs.is_empty() {
+        preserve_dirs(
+            &path_content,
+            source_path,
+            destination_path,
+            &list_of_errors,
+            copy_target,
+            &preserve,
+        );
+    }
+
     let list_of_errors = if let Ok(list_of_errors) = Arc::try_unwrap(list_of_errors) {
         list_of_errors.into_inner().unwrap_or(vec![])
     } else {
@@ -235,6 +620,17 @@ pub fn execute(cmd: Command) {
     };
 
     if list_of_errors.is_empty() {
+        if let Some(journal) = journal {
+            let journal_path = journal.path().to_path_buf();
+
+            if let Err(e) = journal.remove() {
+                eprintln!(
+                    "Unable to remove the copy journal {}: {e}",
+                    journal_path.display()
+                );
+            }
+        }
+
         println!(
             "Copied {} files and {} directories from {} ({} entries, {})",
             path_content.list_of_files.len(),
@@ -243,6 +639,20 @@ pub fn execute(cmd: Command) {
             path_content.entries,
             round_bytes_size(path_content.size)
         );
+
+        if let Some(MirrorEntries {
+            root,
+            conflicting,
+            extraneous,
+        }) = &mirror_entries
+        {
+            println!(
+                "Removed {} files and {} directories from {} that were not in the source or whose type changed",
+                conflicting.list_of_files.len() + extraneous.list_of_files.len(),
+                conflicting.list_of_dirs.len() + extraneous.list_of_dirs.len(),
+                root.display()
+            );
+        }
     } else {
         eprintln!(
             "{} error(s) occurred during the copy :",
@@ -251,7 +661,163 @@ pub fn execute(cmd: Command) {
         for error in list_of_errors {
             eprintln!("- {error}");
         }
+
+        if journal.is_some() {
+            eprintln!("The copy can be resumed with --resume once the errors are fixed");
+        }
+    }
+}
+
+/// Compute what the copy would do, without changing anything.
+/// The decisions are the same as the ones made by `copy_dirs` and `copy_files`.
+pub fn plan_copy(
+    path_content: &PathContent,
+    source_path: &Path,
+    destination_path: &Path,
+    copy_target: bool,
+    option: &OptionsTypes,
+) -> Plan {
+    let mut plan = Plan::new(destination_path);
+
+    if source_path.is_dir() && !destination_path.exists() {
+        plan.add(PlanAction::Create, destination_path, true, None);
+    }
+
+    for dir in &path_content.list_of_dirs {
+        let Some(destination_dir) =
+            destination_for(dir, source_path, destination_path, copy_target)
+        else {
+            continue;
+        };
+
+        let action = if destination_dir.is_dir() {
+            PlanAction::Skip
+        } else {
+            PlanAction::Create
+        };
+
+        plan.add(action, &destination_dir, true, None);
+    }
+
+    for file in &path_content.list_of_files {
+        let Some(destination_file) =
+            destination_for(file, source_path, destination_path, copy_target)
+        else {
+            continue;
+        };
+
+        let is_link = path_content.is_link_entry(file);
+
+        let need_copy = if is_link {
+            need_link_copy(&destination_file, option)
+        } else {
+            // An error reading the metadata would make the copy of this file fail
+            need_copy(file, &destination_file, option).unwrap_or(false)
+        };
+
+        let action = if !need_copy {
+            PlanAction::Skip
+        } else if destination_file.symlink_metadata().is_ok() {
+            PlanAction::Overwrite
+        } else {
+            PlanAction::Create
+        };
+
+        let size = if is_link {
+            None
+        } else {
+            file.metadata().ok().map(|metadata| metadata.len())
+        };
+
+        plan.add(action, &destination_file, false, size);
+    }
+
+    plan
+}
+
+/// Index the destination path and returns the entries to remove in mirror mode, with the path
+/// they were indexed from (the destination path, or the copied folder in it if `copy_target` is true).
+/// The filter of the source is applied to the destination too, so the excluded entries are never returned.
+/// The entries whose type changed (a file replaced by a folder for example) are returned apart,
+/// with the content of the conflicting folders, since they must be removed before the copy.
+pub fn mirror_extraneous(
+    path_content: &PathContent,
+    source_path: &Path,
+    destination_path: &Path,
+    copy_target: bool,
+) -> std::io::Result<MirrorEntries> {
+    let Some(mirror_root) =
+        destination_for(source_path, source_path, destination_path, copy_target)
+    else {
+        return Err(std::io::Error::other(
+            "Impossible to determine the mirrored folder in the destination path",
+        ));
+    };
+
+    // Indexed like the source, so the same entries are compared
+    let mut destination_content = PathContent::new()
+        .with_symlinks(path_content.symlinks)
+        .with_filter(path_content.filter.clone());
+
+    if !mirror_root.is_dir() {
+        // Nothing to remove
+        return Ok(MirrorEntries {
+            root: mirror_root,
+            conflicting: destination_content.clone(),
+            extraneous: destination_content,
+        });
     }
+
+    destination_content.index_entries(&mirror_root, false, &IgnoreFlag::None)?;
+
+    let expected_dirs = path_content.list_of_dirs.iter().map(|dir| (dir, true));
+    let expected_files = path_content.list_of_files.iter().map(|file| (file, false));
+
+    // The destination path of every source entry, and whether it is a directory
+    let expected: HashMap<PathBuf, bool> = expected_dirs
+        .chain(expected_files)
+        .filter_map(|(entry, is_dir)| {
+            Some((
+                destination_for(entry, source_path, destination_path, copy_target)?,
+                is_dir,
+            ))
+        })
+        .collect();
+
+    let changed_dirs = destination_content
+        .list_of_dirs
+        .iter()
+        .filter(|dir| expected.get(*dir) == Some(&false));
+    let changed_files = destination_content
+        .list_of_files
+        .iter()
+        .filter(|file| expected.get(*file) == Some(&true));
+
+    let changed: HashSet<PathBuf> = changed_dirs.chain(changed_files).cloned().collect();
+
+    let is_conflicting = |entry: &Path| {
+        entry
+            .ancestors()
+            .take_while(|ancestor| *ancestor != mirror_root)
+            .any(|ancestor| changed.contains(ancestor))
+    };
+
+    let mut conflicting = destination_content.clone();
+    conflicting.retain_entries(|entry, _| is_conflicting(entry));
+
+    destination_content.retain_entries(|entry, is_dir| {
+        expected.get(entry) != Some(&is_dir) && !is_conflicting(entry)
+    });
+
+    // The directories containing excluded entries must be kept
+    conflicting.drop_partial_dirs();
+    destination_content.drop_partial_dirs();
+
+    Ok(MirrorEntries {
+        root: mirror_root,
+        conflicting,
+        extraneous: destination_content,
+    })
 }
 
 /// Copy directories from the source path to the destination path.
@@ -259,6 +825,7 @@ pub fn execute(cmd: Command) {
 /// Note: because of the parallel processing, a flag protected by a mutex is used to track the status.
 /// At the end of the process, the mutex is unwrapped to get the final status. If an error with the mutex occurs,
 /// the function returns false.
+/// If a journal is given, the directories it lists as created are skipped and the new ones are recorded.
 #[allow(clippy::module_name_repetitions)]
 pub fn copy_dirs(
     path_content: &PathContent,
@@ -266,6 +833,7 @@ pub fn copy_dirs(
     destination_path: &Path,
     list_of_errors: &Arc<Mutex<Vec<String>>>,
     copy_target: bool,
+    journal: Option<&CopyJournal>,
 ) -> bool {
     let pb = progress_bar_helper::create_progress(path_content.list_of_dirs.len() as u64);
 
@@ -273,20 +841,17 @@ pub fn copy_dirs(
 
     let is_ok = Mutex::new(true);
 
-    path_content.list_of_dirs.par_iter().for_each(|dir| {
-        let relative_path = if copy_target {
-            let Some(parent_path) = source_path.parent() else {
-                add_error(
-                    list_of_errors,
-                    format!("Impossible to determine parent path for {source_path:?}"),
-                );
-                if let Ok(mut is_ok) = is_ok.lock() {
-                    *is_ok = false;
-                }
+    path_content
+        .list_of_dirs
+        .par_iter()
+        .enumerate()
+        .for_each(|(index, dir)| {
+            if journal.is_some_and(|journal| journal.created_dirs.contains(&index)) {
+                pb.inc(1);
                 return;
-            };
+            }
 
-            let Ok(rel_path) = dir.strip_prefix(parent_path) else {
+            let Some(relative_path) = relative_path(dir, source_path, copy_target) else {
                 add_error(
                     list_of_errors,
                     format!("Impossible to determine relative path for {dir:?}"),
@@ -297,46 +862,41 @@ pub fn copy_dirs(
                 return;
             };
 
-            rel_path
-        } else {
-            let Ok(rel_path) = dir.strip_prefix(source_path) else {
+            let destination_dir = destination_path.join(relative_path);
+
+            // Do the copy of the directories
+            if let Err(e) = create_dir_all(&destination_dir) {
                 add_error(
                     list_of_errors,
-                    format!("Impossible to determine relative path for {dir:?}"),
+                    format!("Unable to create directory {destination_dir:?}: {e:?}"),
                 );
                 if let Ok(mut is_ok) = is_ok.lock() {
                     *is_ok = false;
                 }
                 return;
-            };
-
-            rel_path
-        };
-
-        let destination_dir = destination_path.join(relative_path);
+            }
 
-        // Do the copy of the directories
-        if let Err(e) = create_dir_all(&destination_dir) {
-            add_error(
+            record_step(
+                journal,
+                JournalEntry::DirCreated(index),
+                &destination_dir,
                 list_of_errors,
-                format!("Unable to create directory {destination_dir:?}: {e:?}"),
             );
-            if let Ok(mut is_ok) = is_ok.lock() {
-                *is_ok = false;
-            }
-            return;
-        }
 
-        pb.inc(1);
-    });
+            pb.inc(1);
+        });
 
     pb.finish_with_message("Directories copied");
 
     is_ok.into_inner().unwrap_or(false)
 }
 
-/// Returns a vector with the paths of the copied files (source and destination)
-#[allow(clippy::module_name_repetitions)]
+/// Returns the copied files, with their index in the path content and their paths (source and destination).
+/// If a verification is given, the source bytes are hashed during the copy so only the destination is re-read.
+/// If a journal is given, the files it lists as copied are skipped once verified (the ones that don't match
+/// their source are copied again) and the new ones are recorded.
+/// The attributes selected in `preserve` are applied to every copied file, and the mode always is.
+#[allow(clippy::module_name_repetitions, clippy::too_many_arguments)]
 pub fn copy_files(
     path_content: &PathContent,
     source_path: &Path,
@@ -344,111 +904,141 @@ pub fn copy_files(
     list_of_errors: &Arc<Mutex<Vec<String>>>,
     copy_target: bool,
     option: &OptionsTypes,
-) -> Vec<(PathBuf, PathBuf)> {
+    preserve: &Preserve,
+    verification: Option<&Verification>,
+    journal: Option<&CopyJournal>,
+) -> Vec<CopiedFile> {
     let pb = progress_bar_helper::create_progress(path_content.list_of_files.len() as u64);
 
     pb.set_message("Copying files");
 
-    let copied_files: Arc<Mutex<Vec<(PathBuf, PathBuf)>>> = Arc::new(Mutex::new(Vec::new()));
+    let copied_files: Arc<Mutex<Vec<CopiedFile>>> = Arc::new(Mutex::new(Vec::new()));
 
-    path_content.list_of_files.par_iter().for_each(|file| {
-        let relative_path = if copy_target {
-            let Some(parent_path) = source_path.parent() else {
-                add_error(
-                    list_of_errors,
-                    format!("Impossible to determine parent path for {source_path:?}"),
-                );
+    // The files skipped because they are identical, with the checksum or size-only options
+    let identical_files = AtomicUsize::new(0);
+
+    // The files keep the mode of the source like std::fs::copy does, it is set after the other attributes
+    let preserve = &Preserve {
+        mode: true,
+        ..preserve.clone()
+    };
+
+    path_content.list_of_files.par_iter().enumerate().for_each(|(index, file)| {
+        let Some(destination_file) =
+            destination_for(file, source_path, destination_path, copy_target)
+        else {
+            add_error(
+                list_of_errors,
+                format!("Impossible to determine relative path for {file:?}"),
+            );
+            return;
+        };
+
+        // A file copied by the interrupted copy but whose verification failed is copied again
+        let mut recopy = false;
+
+        if let Some(journal) = journal
+            && journal.copied_files.contains(&index)
+        {
+            if journal.verified_files.contains(&index) {
+                pb.inc(1);
                 return;
-            };
+            }
 
-            let Ok(rel_path) = file.strip_prefix(parent_path) else {
-                add_error(
-                    list_of_errors,
-                    format!("Impossible to determine relative path for {file:?}"),
-                );
+            // Already copied by the interrupted copy, only the verification may be missing
+            match verification {
+                // The file was flushed to the disk before it was recorded, trust it
+                None => {
+                    pb.inc(1);
+                    return;
+                }
+                Some(verification)
+                    if same_content(file, &destination_file, &verification.algorithm) =>
+                {
+                    record_step(
+                        Some(journal),
+                        JournalEntry::FileVerified(index),
+                        &destination_file,
+                        list_of_errors,
+                    );
+
+                    pb.inc(1);
+                    return;
+                }
+                Some(_) => recopy = true,
+            }
+        }
+
+        if path_content.is_link_entry(file) {
+            if !need_link_copy(&destination_file, option) {
+                pb.inc(1);
                 return;
-            };
+            }
 
-            rel_path
-        } else {
-            let Ok(rel_path) = file.strip_prefix(source_path) else {
+            if let Err(e) = copy_link(file, &destination_file) {
                 add_error(
                     list_of_errors,
-                    format!("Impossible to determine relative path for {file:?}"),
+                    format!("Error copying link {file:?} to {destination_file:?}: {e:?}"),
                 );
                 return;
-            };
+            }
 
-            rel_path
-        };
+            // The link target is copied as it is, there is nothing to verify
+            record_step(journal, JournalEntry::FileCopied(index), &destination_file, list_of_errors);
+            record_step(journal, JournalEntry::FileVerified(index), &destination_file, list_of_errors);
 
-        // Check if the relative path is not empty and join it with the destination path
-        let destination_file = if relative_path.to_str().unwrap_or_default().is_empty() {
-            destination_path.to_path_buf()
-        } else {
-            destination_path.join(relative_path)
-        };
+            pb.inc(1);
+            return;
+        }
 
-        let need_copy = match option {
-            OptionsTypes::None | OptionsTypes::Replace => true,
-            OptionsTypes::Complete => !destination_file.exists(),
-            OptionsTypes::Update => {
-                if destination_file.exists() {
-                    let Ok(source_metadata) = file.metadata() else {
-                        add_error(
-                            list_of_errors,
-                            format!("Error reading metadata for file {file:?}"),
-                        );
-                        return;
-                    };
-
-                    let Ok(destination_metadata) = destination_file.metadata() else {
-                        add_error(
-                            list_of_errors,
-                            format!("Error reading metadata for file {destination_file:?}"),
-                        );
-                        return;
-                    };
-
-                    let Ok(source_modified) = source_metadata.modified() else {
-                        add_error(
-                            list_of_errors,
-                            format!("Error reading modified time for file {file:?}"),
-                        );
-                        return;
-                    };
-
-                    let Ok(destination_modified) = destination_metadata.modified() else {
-                        add_error(
-                            list_of_errors,
-                            format!(
-                                "Error reading modified time for file {destination_file:?}"
-                            ),
-                        );
-                        return;
-                    };
-
-                    source_modified > destination_modified
-                } else {
-                    true
-                }
+        let need_copy = match need_copy(file, &destination_file, option) {
+            Ok(need_copy) => recopy || need_copy,
+            Err(e) => {
+                add_error(list_of_errors, e);
+                return;
             }
         };
 
         if need_copy {
-            // Do the copy of the files
-            if let Err(e) = copy(file, &destination_file) {
+            // Do the copy of the files, hashing the source bytes if they are verified.
+            // With a journal, the file is flushed to the disk before it is recorded as copied,
+            // since a resumed copy doesn't copy it again
+            let source_hash = match copy_file(
+                file,
+                &destination_file,
+                verification.map(|verification| &verification.algorithm),
+                journal.is_some()
+                    || verification.is_some_and(|verification| verification.mode == VerifyMode::Fast),
+            ) {
+                Ok(source_hash) => source_hash,
+                Err(e) => {
+                    add_error(
+                        list_of_errors,
+                        format!(
+                            "Error copying file {file:?} to {destination_file:?}: {e:?}"
+                        ),
+                    );
+                    return;
+                }
+            };
+
+            if let Err(e) = preserve.apply(file, &destination_file) {
                 add_error(
                     list_of_errors,
-                    format!(
-                        "Error copying file {file:?} to {destination_file:?}: {e:?}"
-                    ),
+                    format!("Error preserving attributes of file {file:?} on {destination_file:?}: {e:?}"),
                 );
                 return;
             }
 
+            record_step(journal, JournalEntry::FileCopied(index), &destination_file, list_of_errors);
+
             match copied_files.lock() {
-                Ok(mut copied_files) => copied_files.push((file.clone(), destination_file)),
+                Ok(mut copied_files) => copied_files.push(CopiedFile {
+                    index,
+                    source: file.clone(),
+                    destination: destination_file,
+                    source_hash,
+                }),
                 Err(_) => {
                     add_error(
                         list_of_errors,
@@ -458,6 +1048,8 @@ pub fn copy_files(
                     );
                 }
             }
+        } else if matches!(option, OptionsTypes::Checksum(_) | OptionsTypes::SizeOnly) {
+            identical_files.fetch_add(1, Ordering::Relaxed);
         }
 
         pb.inc(1);
@@ -465,6 +1057,13 @@ pub fn copy_files(
 
     pb.finish_with_message("Files copied");
 
+    if matches!(option, OptionsTypes::Checksum(_) | OptionsTypes::SizeOnly) {
+        println!(
+            "{} files skipped as identical",
+            identical_files.into_inner()
+        );
+    }
+
     if let Ok(copied_files) = Arc::try_unwrap(copied_files) {
         copied_files.into_inner().unwrap_or(Vec::new())
     } else {
@@ -473,64 +1072,166 @@ pub fn copy_files(
     }
 }
 
+/// Verify that the copied files are identical to their source.
+/// The source files are only read if their digest wasn't computed during the copy, and in fast mode
+/// the destination files are only read if the source files had to be read.
+/// If a journal is given, the verified files are recorded.
 #[allow(clippy::module_name_repetitions)]
 pub fn verify_copy(
-    copied_files: &Vec<(PathBuf, PathBuf)>,
+    copied_files: &Vec<CopiedFile>,
+    verification: &Verification,
     list_of_errors: &Arc<Mutex<Vec<String>>>,
+    journal: Option<&CopyJournal>,
 ) {
     let pb = progress_bar_helper::create_progress(copied_files.len() as u64);
 
     pb.set_message("Verifying files");
 
-    copied_files
-        .par_iter()
-        .for_each(|(source_file, destination_file)| {
-            let Ok(mut source_file) = File::open(source_file) else {
-                add_error(
-                    list_of_errors,
-                    format!("Error opening source file {source_file:?}"),
-                );
-                return;
-            };
+    copied_files.par_iter().for_each(|copied_file| {
+        let CopiedFile {
+            index,
+            source: source_file,
+            destination: destination_file_path,
+            source_hash,
+        } = copied_file;
+
+        if verification.mode == VerifyMode::Fast && source_hash.is_some() {
+            // The destination file was flushed to the disk during the copy, trust the write
+            record_step(
+                journal,
+                JournalEntry::FileVerified(*index),
+                destination_file_path,
+                list_of_errors,
+            );
+
+            pb.inc(1);
+            return;
+        }
+
+        let source_hash = match source_hash {
+            Some(hash) => hash.clone(),
+            None => match verification.algorithm.compute_file(source_file) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    add_error(
+                        list_of_errors,
+                        format!("Error reading source file {source_file:?}: {e:?}"),
+                    );
+                    return;
+                }
+            },
+        };
 
-            let mut source_buffer = Vec::new();
-            if source_file.read_to_end(&mut source_buffer).is_err() {
+        let destination_hash = match verification.algorithm.compute_file(destination_file_path) {
+            Ok(hash) => hash,
+            Err(e) => {
                 add_error(
                     list_of_errors,
-                    format!("Error reading source file {source_file:?}"),
+                    format!("Error reading destination file {destination_file_path:?}: {e:?}"),
                 );
                 return;
             }
+        };
 
-            let source_hash = HashAlgorithm::Sha2_256.compute(source_buffer);
+        if source_hash != destination_hash {
+            add_error(
+                list_of_errors,
+                format!(
+                    "Hashes don't match for files {source_file:?} and {destination_file_path:?}"
+                ),
+            );
+            return;
+        }
 
-            let Ok(mut destination_file) = File::open(destination_file) else {
-                add_error(
-                    list_of_errors,
-                    format!("Error opening destination file {destination_file:?}"),
-                );
-                return;
-            };
+        record_step(
+            journal,
+            JournalEntry::FileVerified(*index),
+            destination_file_path,
+            list_of_errors,
+        );
 
-            let mut destination_buffer = Vec::new();
-            if destination_file
-                .read_to_end(&mut destination_buffer)
-                .is_err()
-            {
-                add_error(
-                    list_of_errors,
-                    format!("Error reading destination file {destination_file:?}"),
-                );
-                return;
-            }
+        pb.inc(1);
+    });
+
+    pb.finish_with_message("Files verified");
+}
+
+/// Returns true if the destination file has the same digest as the source file.
+/// A file that can't be read is considered different.
+fn same_content(source: &Path, destination: &Path, algorithm: &HashAlgorithm) -> bool {
+    match (
+        algorithm.compute_file(source),
+        algorithm.compute_file(destination),
+    ) {
+        (Ok(source_hash), Ok(destination_hash)) => source_hash == destination_hash,
+        _ => false,
+    }
+}
+
+/// Recreate the symbolic link at the destination path, pointing to the same target as the source link.
+/// An existing link or file at the destination path is replaced.
+fn copy_link(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let target = read_link(source)?;
+
+    if destination.symlink_metadata().is_ok() {
+        remove_file(destination)?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&target, destination)?;
+
+    #[cfg(windows)]
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, destination)?;
+    } else {
+        std::os::windows::fs::symlink_file(&target, destination)?;
+    }
+
+    Ok(())
+}
+
+/// Apply the preserved attributes of the source directories to the copied directories.
+/// This is done once all the files have been written, otherwise the modification times
+/// would be changed again and a read-only mode would prevent the copy of the content.
+#[allow(clippy::module_name_repetitions)]
+pub fn preserve_dirs(
+    path_content: &PathContent,
+    source_path: &Path,
+    destination_path: &Path,
+    list_of_errors: &Arc<Mutex<Vec<String>>>,
+    copy_target: bool,
+    preserve: &Preserve,
+) {
+    let pb = progress_bar_helper::create_progress(path_content.list_of_dirs.len() as u64);
+
+    pb.set_message("Preserving directories attributes");
 
-            let destination_hash = HashAlgorithm::Sha2_256.compute(destination_buffer);
+    let mut dirs_by_depth = BTreeMap::new();
 
-            if source_hash != destination_hash {
+    for dir in &path_content.list_of_dirs {
+        let Some(relative_path) = relative_path(dir, source_path, copy_target) else {
+            add_error(
+                list_of_errors,
+                format!("Impossible to determine relative path for {dir:?}"),
+            );
+            continue;
+        };
+
+        dirs_by_depth
+            .entry(relative_path.components().count())
+            .or_insert_with(Vec::new)
+            .push((dir, destination_path.join(relative_path)));
+    }
+
+    // Apply the attributes by depth, starting from the deepest, so a read-only or
+    // non-searchable parent doesn't prevent the changes on its children
+    for dirs in dirs_by_depth.values().rev() {
+        dirs.par_iter().for_each(|(dir, destination_dir)| {
+            if let Err(e) = preserve.apply(dir, destination_dir) {
                 add_error(
                     list_of_errors,
                     format!(
-                        "Hashes don't match for files {source_file:?} and {destination_file:?}"
+                        "Error preserving attributes of directory {dir:?} on {destination_dir:?}: {e:?}"
                     ),
                 );
                 return;
@@ -538,6 +1239,264 @@ pub fn verify_copy(
 
             pb.inc(1);
         });
+    }
 
-    pb.finish_with_message("Files verified");
+    pb.finish_with_message("Directories attributes preserved");
+}
+
+/// Returns true if the file must be copied to the destination file, according to the option.
+/// The error message is returned if the metadata or the content of the files can't be read.
+pub fn need_copy(
+    file: &Path,
+    destination_file: &Path,
+    option: &OptionsTypes,
+) -> Result<bool, String> {
+    match option {
+        OptionsTypes::None | OptionsTypes::Replace => Ok(true),
+        OptionsTypes::Complete => Ok(!destination_file.exists()),
+        OptionsTypes::Update => {
+            if !destination_file.exists() {
+                return Ok(true);
+            }
+
+            let source_modified = file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(|_| format!("Error reading modified time for file {file:?}"))?;
+
+            let destination_modified = destination_file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(|_| {
+                    format!("Error reading modified time for file {destination_file:?}")
+                })?;
+
+            Ok(source_modified > destination_modified)
+        }
+        OptionsTypes::Checksum(_) | OptionsTypes::SizeOnly => {
+            if !destination_file.exists() {
+                return Ok(true);
+            }
+
+            let source_size = file
+                .metadata()
+                .map_err(|_| format!("Error reading metadata for file {file:?}"))?
+                .len();
+
+            let destination_size = destination_file
+                .metadata()
+                .map_err(|_| format!("Error reading metadata for file {destination_file:?}"))?
+                .len();
+
+            if source_size != destination_size {
+                return Ok(true);
+            }
+
+            let OptionsTypes::Checksum(algorithm) = option else {
+                return Ok(false);
+            };
+
+            let source_hash = algorithm
+                .compute_file(file)
+                .map_err(|_| format!("Error reading file {file:?}"))?;
+
+            let destination_hash = algorithm
+                .compute_file(destination_file)
+                .map_err(|_| format!("Error reading file {destination_file:?}"))?;
+
+            Ok(source_hash != destination_hash)
+        }
+    }
+}
+
+/// Returns true if the link must be copied to the destination path, according to the option.
+/// Links are always replaced, except with the complete option.
+pub fn need_link_copy(destination_file: &Path, option: &OptionsTypes) -> bool {
+    *option != OptionsTypes::Complete || destination_file.symlink_metadata().is_err()
+}
+
+/// Returns the destination path of an entry of the source path.
+/// If the source path is a file copied directly (not into the destination), this is the destination path itself.
+pub fn destination_for(
+    entry: &Path,
+    source_path: &Path,
+    destination_path: &Path,
+    copy_target: bool,
+) -> Option<PathBuf> {
+    let relative_path = relative_path(entry, source_path, copy_target)?;
+
+    if relative_path.as_os_str().is_empty() {
+        Some(destination_path.to_path_buf())
+    } else {
+        Some(destination_path.join(relative_path))
+    }
+}
+
+/// Returns the path of the entry relative to the source path, as it will be placed in the destination.
+/// If `copy_target` is true, the source path itself is part of the relative path.
+pub fn relative_path<'a>(
+    entry: &'a Path,
+    source_path: &Path,
+    copy_target: bool,
+) -> Option<&'a Path> {
+    if copy_target {
+        entry.strip_prefix(source_path.parent()?).ok()
+    } else {
+        entry.strip_prefix(source_path).ok()
+    }
+}
+
+/// Describe the copy options covered by the fingerprint of the journal, with stable names.
+fn journal_options(
+    option: &OptionsTypes,
+    preserve: &Preserve,
+    verification: Option<&Verification>,
+) -> String {
+    let option = match option {
+        OptionsTypes::None => "none".to_string(),
+        OptionsTypes::Replace => "replace".to_string(),
+        OptionsTypes::Complete => "complete".to_string(),
+        OptionsTypes::Update => "update".to_string(),
+        OptionsTypes::Checksum(algorithm) => format!("checksum:{algorithm}"),
+        OptionsTypes::SizeOnly => "size-only".to_string(),
+    };
+
+    let preserve: Vec<&str> = [
+        (preserve.mode, "mode"),
+        (preserve.timestamps, "timestamps"),
+        (preserve.ownership, "ownership"),
+    ]
+    .into_iter()
+    .filter_map(|(preserved, name)| preserved.then_some(name))
+    .collect();
+
+    let verification = match verification {
+        None => "none".to_string(),
+        Some(Verification {
+            algorithm,
+            mode: VerifyMode::Full,
+        }) => format!("full:{algorithm}"),
+        Some(Verification {
+            algorithm,
+            mode: VerifyMode::Fast,
+        }) => format!("fast:{algorithm}"),
+    };
+
+    format!(
+        "copy={option} preserve={} verify={verification}",
+        preserve.join(",")
+    )
+}
+
+/// Record a step of the copy in the journal, if there is one.
+/// If the step cannot be recorded, an error is added to the list of errors.
+fn record_step(
+    journal: Option<&CopyJournal>,
+    entry: JournalEntry,
+    path: &Path,
+    list_of_errors: &Arc<Mutex<Vec<String>>>,
+) {
+    if let Some(journal) = journal
+        && let Err(e) = journal.record(entry)
+    {
+        add_error(
+            list_of_errors,
+            format!("Error recording {path:?} in the journal: {e:?}"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_journal_options() {
+        let preserve = Preserve {
+            mode: true,
+            ownership: true,
+            ..Preserve::default()
+        };
+        let verification = Verification {
+            algorithm: HashAlgorithm::Blake3,
+            mode: VerifyMode::Fast,
+        };
+
+        assert_eq!(
+            journal_options(
+                &OptionsTypes::Checksum(HashAlgorithm::Sha2_256),
+                &preserve,
+                Some(&verification)
+            ),
+            "copy=checksum:SHA2-256 preserve=mode,ownership verify=fast:BLAKE3"
+        );
+        assert_eq!(
+            journal_options(&OptionsTypes::None, &Preserve::default(), None),
+            "copy=none preserve= verify=none"
+        );
+    }
+
+    #[test]
+    fn test_resume_repairs_copied_files() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        let destination = dir.path().join("destination");
+        create_dir_all(&source).unwrap();
+        create_dir_all(&destination).unwrap();
+        write(source.join("file.txt"), b"Hello, world!").unwrap();
+
+        // The interrupted copy recorded the file as copied, but its content didn't reach the disk
+        write(destination.join("file.txt"), b"").unwrap();
+        let journal = CopyJournal::create(&destination, "fingerprint").unwrap();
+        journal.record(JournalEntry::FileCopied(0)).unwrap();
+        drop(journal);
+
+        let mut path_content = PathContent::new();
+        path_content
+            .index_entries(&source, false, &IgnoreFlag::default())
+            .unwrap();
+
+        let journal = CopyJournal::load(&destination, "fingerprint").unwrap();
+        let list_of_errors = Arc::new(Mutex::new(vec![]));
+        let verification = Verification {
+            algorithm: HashAlgorithm::Sha2_256,
+            mode: VerifyMode::Full,
+        };
+
+        let copied_files = copy_files(
+            &path_content,
+            &source,
+            &destination,
+            &list_of_errors,
+            false,
+            &OptionsTypes::None,
+            &Preserve::default(),
+            Some(&verification),
+            Some(&journal),
+        );
+
+        // The file doesn't match its source, so it is copied again
+        assert_eq!(
+            read(destination.join("file.txt")).unwrap(),
+            b"Hello, world!"
+        );
+        assert_eq!(copied_files.len(), 1);
+
+        verify_copy(
+            &copied_files,
+            &verification,
+            &list_of_errors,
+            Some(&journal),
+        );
+
+        assert!(list_of_errors.lock().unwrap().is_empty());
+        drop(journal);
+
+        let journal = CopyJournal::load(&destination, "fingerprint").unwrap();
+        assert!(journal.verified_files.contains(&0));
+
+        journal.remove().unwrap();
+    }
 }
@@ -0,0 +1,97 @@
+use crate::utils::hash::{HASH_BUFFER_SIZE, HashAlgorithm};
+use clap::ValueEnum;
+use std::fs::{File, copy};
+use std::io::{ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum VerifyMode {
+    /// Re-read the destination files and compare their digest with the digest of the source
+    #[default]
+    Full,
+
+    /// Flush the destination files to the disk and trust the write, without re-reading them
+    Fast,
+}
+
+/// How the copied files are verified.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// The algorithm of the digests compared
+    pub algorithm: HashAlgorithm,
+
+    /// Whether the destination files are re-read
+    pub mode: VerifyMode,
+}
+
+/// Copy the content of the source file to the destination file.
+/// Without algorithm nor `sync`, `std::fs::copy` is used so the fast paths of the platform
+/// (`copy_file_range`, reflinks) apply. Otherwise the bytes are streamed through a bounded buffer,
+/// hashed while they are copied if an algorithm is given (the digest is returned), and flushed
+/// to the disk before returning if `sync` is true.
+/// The mode is left to `Preserve::apply`, which sets it after the other attributes.
+pub fn copy_file(
+    source: &Path,
+    destination: &Path,
+    algorithm: Option<&HashAlgorithm>,
+    sync: bool,
+) -> Result<Option<Vec<u8>>> {
+    if algorithm.is_none() && !sync {
+        copy(source, destination)?;
+        return Ok(None);
+    }
+
+    let mut source_file = File::open(source)?;
+    let mut destination_file = File::create(destination)?;
+
+    let mut hasher = algorithm.map(HashAlgorithm::hasher);
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+
+    loop {
+        let read = match source_file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..read]);
+        }
+
+        destination_file.write_all(&buffer[..read])?;
+    }
+
+    if sync {
+        destination_file.sync_all()?;
+    }
+
+    Ok(hasher.map(|hasher| hasher.finalize().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_copy_file_hash() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source.bin");
+        let destination = dir.path().join("destination.bin");
+
+        // The pattern doesn't repeat at the buffer size, so a chunk written twice or skipped changes the copy
+        let content: Vec<u8> = (0..HASH_BUFFER_SIZE * 3 + 11)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        write(&source, &content).unwrap();
+
+        let hash = copy_file(&source, &destination, Some(&HashAlgorithm::Sha2_256), true).unwrap();
+
+        assert_eq!(read(&destination).unwrap(), content);
+        assert_eq!(hash, Some(HashAlgorithm::Sha2_256.compute(&content)));
+
+        assert_eq!(copy_file(&source, &destination, None, false).unwrap(), None);
+    }
+}
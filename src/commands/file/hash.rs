@@ -1,56 +1,588 @@
-use std::{fs::File, io::Read, path::Path};
+use std::collections::HashSet;
+use std::fs::{File, read_link, read_to_string};
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, absolute};
 
 use clap::{Args, builder};
 use hex::encode;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::utils::hash::HashAlgorithm;
+use crate::commands::BaseCmdOpt;
+use crate::commands::file::FilterCmdOpt;
+use crate::commands::file::manifest::{ManifestFormat, format_line, parse_line};
+use crate::path_content::{IgnoreFlag, PathContent, SymlinkPolicy};
+use crate::progress_bar_helper;
+use crate::utils::confirm_continue;
+use crate::utils::hash::{DigestEncoding, HashAlgorithm};
+
+/// The path of a file with its hashes, in the order of the selected algorithms.
+type FileHashes = (String, Vec<Vec<u8>>);
 
 #[derive(Args, Clone)]
 pub struct Command {
     #[arg(
-        required = true,
+        required_unless_present = "check",
         value_parser = builder::NonEmptyStringValueParser::new(),
-        help = "The source path to hash."
+        help = "The source path to hash. If it is a directory, every file in it is hashed, followed by a digest of the whole tree."
     )]
-    source: String,
+    source: Option<String>,
+
+    #[clap(flatten)]
+    base: BaseCmdOpt,
 
     #[arg(
         short,
         long,
         default_value = "sha2-256",
         value_enum,
-        action = clap::ArgAction::Set,
-        num_args = 1,
+        action = clap::ArgAction::Append,
+        num_args(1..),
+        value_delimiter = ',',
+        ignore_case = true,
+        help = "Specify the hash algorithms to use. The files are read once for all the algorithms."
+    )]
+    algorithm: Vec<HashAlgorithm>,
+
+    #[arg(
+        short,
+        long,
+        default_value = "hex",
+        value_enum,
+        ignore_case = true,
+        help = "The encoding of the printed digests. The manifests are always written in hex."
+    )]
+    encoding: DigestEncoding,
+
+    #[arg(
+        long,
+        default_value = "copy-link",
+        value_enum,
+        ignore_case = true,
+        help = "How to handle symbolic links in a directory: hash the target path of the links (copy-link), follow them or skip them."
+    )]
+    symlinks: SymlinkPolicy,
+
+    #[clap(flatten)]
+    filter: FilterCmdOpt,
+
+    #[arg(
+        long,
+        default_value = "gnu",
+        value_enum,
         ignore_case = true,
-        help = "Specify the hash algorithm to use."
+        help = "The format of the lines of the files in a directory and in the manifest: gnu (sha256sum) or bsd (shasum --tag)."
+    )]
+    format: ManifestFormat,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Write a checksum manifest of the hashed files to the given path. \
+                The paths are written as seen from the current directory, like sha256sum does."
+    )]
+    manifest: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        conflicts_with_all = ["source", "manifest"],
+        help = "Hash the files listed in the given manifest and report them as OK, FAILED, MISSING or FAILED open or read. \
+                The paths are resolved from the current directory. \
+                The lines without algorithm (gnu format) are checked with the --algorithm whose digest length matches."
     )]
-    algorithm: HashAlgorithm,
+    check: Option<String>,
 }
 
 impl Command {
     pub fn execute(&self) {
-        let source_path = Path::new(&self.source);
+        if let Some(manifest) = &self.check {
+            if !self.check_manifest(Path::new(manifest)) {
+                std::process::exit(1);
+            }
+            return;
+        }
+
+        let Some(source) = &self.source else {
+            eprintln!("The source path is required.");
+            return;
+        };
+
+        let source_path = Path::new(source);
+
+        if self.manifest.is_some() && self.format == ManifestFormat::Gnu && self.algorithm.len() > 1
+        {
+            eprintln!("A manifest with several algorithms must use the bsd format (--format bsd).");
+            return;
+        }
+
+        if let Some(algorithm) = self
+            .algorithm
+            .iter()
+            .find(|algorithm| !self.encoding.supports(algorithm))
+        {
+            eprintln!("The {algorithm} digests can't be printed with the sri encoding.");
+            return;
+        }
+
+        for algorithm in &self.algorithm {
+            algorithm.warn_if_insecure();
+        }
+
+        if source_path.is_dir() {
+            self.hash_dir(source, source_path);
+            return;
+        }
 
         if !source_path.exists() || !source_path.is_file() {
-            eprintln!("The source path does not exist or is not a file.");
+            eprintln!("The source path does not exist or is not a file or a directory.");
+            return;
+        }
+
+        let Ok(hashes) = HashAlgorithm::compute_file_all(&self.algorithm, source_path) else {
+            eprintln!("Error reading file.");
             return;
+        };
+
+        for (algorithm, hash) in self.algorithm.iter().zip(&hashes) {
+            println!("{algorithm}");
+            println!("{}", self.encoding.encode(algorithm, hash));
         }
 
-        println!("{}", self.algorithm);
+        if let Some(manifest) = &self.manifest {
+            let file_hashes = vec![(source.clone(), hashes)];
+            self.write_manifest(Path::new(manifest), &file_hashes);
+        }
+    }
+
+    /// Hash every file of the directory in parallel and print them with their relative path,
+    /// followed by the digest of the whole tree.
+    /// If a manifest is written, it lists the files with their path from the current directory.
+    fn hash_dir(&self, source: &str, source_path: &Path) {
+        if !self.init_workers() {
+            println!("Aborting hash");
+            return;
+        }
 
-        let Ok(mut file) = File::open(source_path) else {
-            eprintln!("Error opening file.");
+        let Some(filter) = self.filter.to_filter() else {
+            println!("Aborting hash");
             return;
         };
 
-        let mut buffer = Vec::new();
-        if file.read_to_end(&mut buffer).is_err() {
-            eprintln!("Error reading file.");
+        let mut path_content = PathContent::new()
+            .with_symlinks(self.symlinks)
+            .with_filter(filter);
+
+        if path_content
+            .index_entries(source_path, false, &IgnoreFlag::Directories)
+            .is_err()
+        {
+            eprintln!("Error indexing source path, aborting hash");
+            return;
+        }
+
+        // The manifest may be written in the hashed directory, it must not list itself
+        if let Some(manifest_path) = self.manifest.as_deref().and_then(|m| absolute(m).ok()) {
+            path_content
+                .retain_entries(|path, _| absolute(path).is_ok_and(|path| path != manifest_path));
+        }
+
+        let pb = progress_bar_helper::create_progress(path_content.list_of_files.len() as u64);
+
+        pb.set_message("Hashing files");
+
+        let results: Vec<Result<(FileHashes, bool), String>> = path_content
+            .list_of_files
+            .par_iter()
+            .map(|file| {
+                let relative_path = file
+                    .strip_prefix(source_path)
+                    .map(portable_path)
+                    .map_err(|_| format!("Impossible to determine relative path for {file:?}"))?;
+
+                let is_link = path_content.is_link_entry(file);
+
+                let hashes = if is_link {
+                    // A link that isn't followed is hashed by its target path
+                    let target = read_link(file)
+                        .map(|target| portable_path(&target))
+                        .map_err(|e| format!("Error reading link {file:?}: {e:?}"))?;
+
+                    self.algorithm
+                        .iter()
+                        .map(|algorithm| algorithm.compute(&target))
+                        .collect()
+                } else {
+                    HashAlgorithm::compute_file_all(&self.algorithm, file)
+                        .map_err(|e| format!("Error reading file {file:?}: {e:?}"))?
+                };
+
+                pb.inc(1);
+
+                Ok(((relative_path, hashes), is_link))
+            })
+            .collect();
+
+        pb.finish_and_clear();
+
+        let mut file_hashes = vec![];
+        let mut links = HashSet::new();
+        let mut errors = vec![];
+
+        for result in results {
+            match result {
+                Ok((file_hash, is_link)) => {
+                    if is_link {
+                        links.insert(file_hash.0.clone());
+                    }
+                    file_hashes.push(file_hash);
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if !errors.is_empty() {
+            eprintln!("Errors occurred while hashing the directory:");
+            for error in errors {
+                eprintln!("{error}");
+            }
             return;
         }
 
-        let hash = encode(self.algorithm.compute(buffer));
+        file_hashes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (position, algorithm) in self.algorithm.iter().enumerate() {
+            let algorithm_hashes: Vec<(String, Vec<u8>)> = file_hashes
+                .iter()
+                .map(|(relative_path, hashes)| (relative_path.clone(), hashes[position].clone()))
+                .collect();
+
+            println!("{algorithm}");
+
+            for (relative_path, hash) in &algorithm_hashes {
+                println!(
+                    "{}",
+                    format_line(
+                        self.format,
+                        algorithm,
+                        relative_path,
+                        &self.encoding.encode(algorithm, hash)
+                    )
+                );
+            }
+
+            println!(
+                "Tree digest: {}",
+                self.encoding.encode(
+                    algorithm,
+                    &tree_digest(algorithm, &algorithm_hashes, &links)
+                )
+            );
+        }
+
+        if let Some(manifest) = &self.manifest {
+            let source = source.trim_end_matches(['/', '\\']);
+
+            let file_hashes: Vec<FileHashes> = file_hashes
+                .into_iter()
+                .map(|(relative_path, hashes)| (format!("{source}/{relative_path}"), hashes))
+                .collect();
+
+            self.write_manifest(Path::new(manifest), &file_hashes);
+        }
+    }
+
+    /// Write the manifest with a line per file and algorithm, in the selected format.
+    fn write_manifest(&self, manifest_path: &Path, file_hashes: &[FileHashes]) {
+        let content: String = file_hashes
+            .iter()
+            .flat_map(|(path, hashes)| {
+                self.algorithm
+                    .iter()
+                    .zip(hashes)
+                    .map(move |(algorithm, hash)| {
+                        format!(
+                            "{}\n",
+                            format_line(self.format, algorithm, path, &encode(hash))
+                        )
+                    })
+            })
+            .collect();
+
+        match File::create(manifest_path).and_then(|mut file| file.write_all(content.as_bytes())) {
+            Ok(()) => println!("Manifest written to {}", manifest_path.display()),
+            Err(e) => eprintln!("Error writing manifest {}: {e}", manifest_path.display()),
+        }
+    }
+
+    /// Hash the files listed in the manifest and print their status.
+    /// Returns true if every listed file matches its digest.
+    fn check_manifest(&self, manifest_path: &Path) -> bool {
+        let content = match read_to_string(manifest_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Error reading manifest {}: {e}", manifest_path.display());
+                return false;
+            }
+        };
+
+        if !self.init_workers() {
+            println!("Aborting check");
+            return false;
+        }
+
+        let mut improper_lines = 0;
+        let mut entries = vec![];
+
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let Some(entry) = parse_line(line) else {
+                improper_lines += 1;
+                continue;
+            };
+
+            // The length of the digest must match the algorithm, the gnu lines use the first
+            // selected algorithm with a matching length
+            let algorithm = match &entry.algorithm {
+                Some(algorithm) if entry.hash.len() == algorithm.output_size() * 2 => {
+                    Some(algorithm.clone())
+                }
+                Some(_) => None,
+                None => self
+                    .algorithm
+                    .iter()
+                    .find(|algorithm| entry.hash.len() == algorithm.output_size() * 2)
+                    .cloned(),
+            };
+
+            match algorithm {
+                Some(algorithm) => entries.push((entry, algorithm)),
+                None => improper_lines += 1,
+            }
+        }
+
+        let mut algorithms: Vec<&HashAlgorithm> = vec![];
+        for (_, algorithm) in &entries {
+            if !algorithms.contains(&algorithm) {
+                algorithm.warn_if_insecure();
+                algorithms.push(algorithm);
+            }
+        }
+
+        let statuses: Vec<CheckStatus> = entries
+            .par_iter()
+            .map(|(entry, algorithm)| {
+                let path = Path::new(&entry.path);
+
+                match algorithm.compute_file(path) {
+                    Ok(hash) if encode(&hash) == entry.hash => CheckStatus::Ok,
+                    Ok(_) => CheckStatus::Failed,
+                    Err(e) if e.kind() == ErrorKind::NotFound => CheckStatus::Missing,
+                    Err(_) => CheckStatus::Unreadable,
+                }
+            })
+            .collect();
+
+        for ((entry, _), status) in entries.iter().zip(&statuses) {
+            println!("{}: {status}", entry.path);
+        }
+
+        let failed = statuses
+            .iter()
+            .filter(|s| **s == CheckStatus::Failed)
+            .count();
+        let missing = statuses
+            .iter()
+            .filter(|s| **s == CheckStatus::Missing)
+            .count();
+        let unreadable = statuses
+            .iter()
+            .filter(|s| **s == CheckStatus::Unreadable)
+            .count();
+
+        if improper_lines > 0 {
+            eprintln!("WARNING: {improper_lines} line(s) are improperly formatted");
+        }
+        if failed > 0 {
+            eprintln!("WARNING: {failed} computed checksum(s) did NOT match");
+        }
+        if missing > 0 {
+            eprintln!("WARNING: {missing} listed file(s) are missing");
+        }
+        if unreadable > 0 {
+            eprintln!("WARNING: {unreadable} listed file(s) could not be read");
+        }
+        if entries.is_empty() {
+            eprintln!("No properly formatted checksum lines found");
+        }
+
+        !entries.is_empty() && failed == 0 && missing == 0 && unreadable == 0
+    }
+
+    /// Set the number of worker threads used to hash the files.
+    /// Returns false if it failed and the user doesn't want to continue.
+    fn init_workers(&self) -> bool {
+        let BaseCmdOpt { workers } = self.base;
+
+        if rayon::ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .build_global()
+            .is_err()
+        {
+            eprintln!(
+                "Error setting the number of threads for rayon, using default value {}",
+                rayon::current_num_threads()
+            );
+
+            return confirm_continue();
+        }
+
+        true
+    }
+}
+
+#[derive(PartialEq)]
+enum CheckStatus {
+    Ok,
+    Failed,
+    /// The file doesn't exist
+    Missing,
+    /// The file exists but can't be opened or read
+    Unreadable,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckStatus::Ok => write!(f, "OK"),
+            CheckStatus::Failed => write!(f, "FAILED"),
+            CheckStatus::Missing => write!(f, "MISSING"),
+            CheckStatus::Unreadable => write!(f, "FAILED open or read"),
+        }
+    }
+}
+
+/// Returns the path with `/` separators, so the same tree gives the same digest on every platform.
+/// An absolute path keeps its leading `/` (and its prefix on Windows), so a link to an absolute
+/// target doesn't hash like a link to the same relative target.
+fn portable_path(path: &Path) -> String {
+    let mut portable = String::new();
+    let mut separator = false;
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => portable.push_str(&prefix.as_os_str().to_string_lossy()),
+            Component::RootDir => portable.push('/'),
+            Component::CurDir => continue,
+            Component::ParentDir | Component::Normal(_) => {
+                if separator {
+                    portable.push('/');
+                }
+                portable.push_str(&component.as_os_str().to_string_lossy());
+            }
+        }
+
+        separator = matches!(component, Component::ParentDir | Component::Normal(_));
+    }
+
+    portable
+}
+
+/// Compute the digest of a tree from the digests of its files.
+/// The files must be sorted by relative path. The digest covers a `<hex digest>  <relative path>`
+/// line per file, so it only depends on the content and the layout of the files.
+/// The links hashed by their target path are marked by a `@` instead of the second space,
+/// so a link can't have the digest of a file containing its target path.
+fn tree_digest(
+    algorithm: &HashAlgorithm,
+    file_hashes: &[(String, Vec<u8>)],
+    links: &HashSet<String>,
+) -> Vec<u8> {
+    let mut hasher = algorithm.hasher();
+
+    for (relative_path, hash) in file_hashes {
+        let separator = if links.contains(relative_path) {
+            '@'
+        } else {
+            ' '
+        };
+
+        hasher.update(format!("{} {separator}{relative_path}\n", encode(hash)).as_bytes());
+    }
+
+    hasher.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_digest() {
+        let algorithm = HashAlgorithm::Sha2_256;
+        let a = ("a.txt".to_string(), algorithm.compute("a"));
+        let b = ("dir/b.txt".to_string(), algorithm.compute("b"));
+        let moved = ("b.txt".to_string(), algorithm.compute("b"));
+
+        let digest = tree_digest(&algorithm, &[a.clone(), b], &HashSet::new());
+
+        assert_eq!(
+            digest,
+            algorithm.compute(format!(
+                "{}  a.txt\n{}  dir/b.txt\n",
+                encode(algorithm.compute("a")),
+                encode(algorithm.compute("b"))
+            ))
+        );
+        assert_ne!(
+            digest,
+            tree_digest(&algorithm, &[a, moved], &HashSet::new())
+        );
+    }
+
+    #[test]
+    fn test_portable_path() {
+        assert_eq!(
+            portable_path(Path::new("dir/sub/file.txt")),
+            "dir/sub/file.txt"
+        );
+        assert_eq!(portable_path(Path::new("./file.txt")), "file.txt");
+        assert_eq!(
+            portable_path(Path::new("../dir/./file.txt")),
+            "../dir/file.txt"
+        );
+        assert_eq!(portable_path(Path::new("/etc/x")), "/etc/x");
+    }
+
+    #[test]
+    fn test_absolute_link_target_digest() {
+        let algorithm = HashAlgorithm::Sha2_256;
+        let link_digest = |target: &str| {
+            let hash = algorithm.compute(portable_path(Path::new(target)));
+            let links = HashSet::from(["link".to_string()]);
+            tree_digest(&algorithm, &[("link".to_string(), hash)], &links)
+        };
+
+        assert_ne!(link_digest("/etc/x"), link_digest("etc/x"));
+    }
+
+    #[test]
+    fn test_link_digest() {
+        let algorithm = HashAlgorithm::Sha2_256;
+        // A file containing the target path of the link has the same hash as the link
+        let entry = ("entry".to_string(), algorithm.compute("target.txt"));
+        let links = HashSet::from(["entry".to_string()]);
+
+        let link_digest = tree_digest(&algorithm, std::slice::from_ref(&entry), &links);
 
-        println!("{hash}");
+        assert_ne!(
+            link_digest,
+            tree_digest(&algorithm, &[entry], &HashSet::new())
+        );
+        assert_eq!(
+            link_digest,
+            algorithm.compute(format!(
+                "{} @entry\n",
+                encode(algorithm.compute("target.txt"))
+            ))
+        );
     }
 }
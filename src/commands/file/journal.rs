@@ -0,0 +1,284 @@
+use crate::path_content::PathContent;
+use crate::utils::hash::HashAlgorithm;
+use hex::encode;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions, remove_file};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result, Write};
+use std::path::{Component, Path, PathBuf, absolute};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+const JOURNAL_HEADER: &str = "clixy-copy-journal 1";
+
+/// A step of the copy recorded in the journal.
+/// Each step references an entry of the (sorted) `PathContent` index by its position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JournalEntry {
+    DirCreated(usize),
+    FileCopied(usize),
+    FileVerified(usize),
+}
+
+/// An on-disk journal of a `file copy` operation, written next to the destination path.
+/// It allows an interrupted copy to be resumed without redoing the finished work.
+#[derive(Debug)]
+pub struct CopyJournal {
+    /// The path of the journal file
+    path: PathBuf,
+
+    /// The journal file, opened in append mode
+    file: Mutex<File>,
+
+    /// The indexes of the directories already created
+    pub created_dirs: HashSet<usize>,
+
+    /// The indexes of the files already copied
+    pub copied_files: HashSet<usize>,
+
+    /// The indexes of the files already verified
+    pub verified_files: HashSet<usize>,
+}
+
+impl CopyJournal {
+    /// Returns the path of the journal for the given destination path.
+    /// The journal is a hidden file placed in the parent folder of the destination.
+    /// The `..` of the destination are resolved so they lead to a named folder, without following
+    /// the links: the journal has the same path whether the destination exists or not.
+    pub fn path_for(destination_path: &Path) -> Result<PathBuf> {
+        let mut resolved_path = PathBuf::new();
+
+        for component in absolute(destination_path)?.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    resolved_path.pop();
+                }
+                component => resolved_path.push(component),
+            }
+        }
+
+        let destination_path = resolved_path;
+
+        let (Some(parent), Some(name)) = (destination_path.parent(), destination_path.file_name())
+        else {
+            return Err(Error::other(
+                "Impossible to determine the journal location for the destination path",
+            ));
+        };
+
+        Ok(parent.join(format!(".{}.clixy-journal", name.to_string_lossy())))
+    }
+
+    /// Create a new journal for the given destination path, replacing any existing one.
+    pub fn create(destination_path: &Path, fingerprint: &str) -> Result<Self> {
+        let path = Self::path_for(destination_path)?;
+
+        let mut file = File::create(&path)?;
+        writeln!(file, "{JOURNAL_HEADER}")?;
+        writeln!(file, "{fingerprint}")?;
+
+        Ok(CopyJournal {
+            path,
+            file: Mutex::new(file),
+            created_dirs: HashSet::new(),
+            copied_files: HashSet::new(),
+            verified_files: HashSet::new(),
+        })
+    }
+
+    /// Load the journal of the given destination path.
+    /// Returns an error if the journal is invalid or if the fingerprint doesn't match,
+    /// meaning the source tree changed since the journal was written.
+    pub fn load(destination_path: &Path, fingerprint: &str) -> Result<Self> {
+        let path = Self::path_for(destination_path)?;
+
+        let mut lines = BufReader::new(File::open(&path)?).lines();
+
+        if lines.next().transpose()?.as_deref() != Some(JOURNAL_HEADER) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The journal file is not a valid copy journal",
+            ));
+        }
+
+        if lines.next().transpose()?.as_deref() != Some(fingerprint) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The source tree changed since the journal was written",
+            ));
+        }
+
+        let mut created_dirs = HashSet::new();
+        let mut copied_files = HashSet::new();
+        let mut verified_files = HashSet::new();
+
+        for line in lines {
+            let line = line?;
+
+            // A partially written last line can be left by an interruption, ignore it
+            let Some((kind, index)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(index) = index.parse::<usize>() else {
+                continue;
+            };
+
+            match kind {
+                "D" => created_dirs.insert(index),
+                "C" => copied_files.insert(index),
+                "V" => verified_files.insert(index),
+                _ => continue,
+            };
+        }
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok(CopyJournal {
+            path,
+            file: Mutex::new(file),
+            created_dirs,
+            copied_files,
+            verified_files,
+        })
+    }
+
+    /// Append a step to the journal.
+    /// The line is synced to the disk before returning, so it survives a crash of the system.
+    /// The caller syncs the data of a step (the copied file) before recording it.
+    pub fn record(&self, entry: JournalEntry) -> Result<()> {
+        let line = match entry {
+            JournalEntry::DirCreated(index) => format!("D {index}\n"),
+            JournalEntry::FileCopied(index) => format!("C {index}\n"),
+            JournalEntry::FileVerified(index) => format!("V {index}\n"),
+        };
+
+        let Ok(mut file) = self.file.lock() else {
+            return Err(Error::other("Error locking the journal file"));
+        };
+
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+
+    /// Returns the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the journal file, consuming the journal.
+    pub fn remove(self) -> Result<()> {
+        drop(self.file);
+        remove_file(self.path)
+    }
+}
+
+/// Compute a fingerprint of the indexed source tree and of the copy options.
+/// It covers the path of every entry and the size and modification time of every file (or link),
+/// so any change in the source tree or in the options produces a different fingerprint.
+pub fn fingerprint(path_content: &PathContent, options: &str) -> Result<String> {
+    let mut hasher = HashAlgorithm::Sha2_256.hasher();
+
+    hasher.update(format!("O {options}\n").as_bytes());
+
+    for dir in &path_content.list_of_dirs {
+        hasher.update(format!("D {}\n", dir.display()).as_bytes());
+    }
+
+    for file in &path_content.list_of_files {
+        let metadata = if path_content.is_link_entry(file) {
+            file.symlink_metadata()?
+        } else {
+            file.metadata()?
+        };
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        hasher.update(format!("F {} {} {modified}\n", file.display(), metadata.len()).as_bytes());
+    }
+
+    Ok(encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_content::IgnoreFlag;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_journal_round_trip() {
+        let dir = tempdir().unwrap();
+        let destination = dir.path().join("destination");
+
+        let journal = CopyJournal::create(&destination, "fingerprint").unwrap();
+        journal.record(JournalEntry::DirCreated(0)).unwrap();
+        journal.record(JournalEntry::FileCopied(1)).unwrap();
+        journal.record(JournalEntry::FileCopied(2)).unwrap();
+        journal.record(JournalEntry::FileVerified(1)).unwrap();
+        drop(journal);
+
+        let journal = CopyJournal::load(&destination, "fingerprint").unwrap();
+
+        assert_eq!(journal.created_dirs, HashSet::from([0]));
+        assert_eq!(journal.copied_files, HashSet::from([1, 2]));
+        assert_eq!(journal.verified_files, HashSet::from([1]));
+
+        journal.remove().unwrap();
+
+        assert!(!CopyJournal::path_for(&destination).unwrap().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journal_path_for_link() {
+        let dir = tempdir().unwrap();
+        let link = dir.path().join("link");
+        let expected = dir.path().join(".link.clixy-journal");
+
+        // The journal path doesn't change once the linked destination exists
+        std::os::unix::fs::symlink("target", &link).unwrap();
+        assert_eq!(CopyJournal::path_for(&link).unwrap(), expected);
+
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        assert_eq!(CopyJournal::path_for(&link).unwrap(), expected);
+        assert_eq!(
+            CopyJournal::path_for(&link.join("child").join("..")).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_journal_fingerprint_mismatch() {
+        let dir = tempdir().unwrap();
+        let destination = dir.path().join("destination");
+
+        drop(CopyJournal::create(&destination, "fingerprint").unwrap());
+
+        let error = CopyJournal::load(&destination, "other").unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_fingerprint_options() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), b"content").unwrap();
+
+        let mut path_content = PathContent::new();
+        path_content
+            .index_entries(dir.path(), false, &IgnoreFlag::default())
+            .unwrap();
+
+        assert_eq!(
+            fingerprint(&path_content, "Checksum").unwrap(),
+            fingerprint(&path_content, "Checksum").unwrap()
+        );
+        assert_ne!(
+            fingerprint(&path_content, "Checksum").unwrap(),
+            fingerprint(&path_content, "SizeOnly").unwrap()
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::utils::hash::HashAlgorithm;
+use clap::ValueEnum;
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum ManifestFormat {
+    /// `<hex>  <path>`, as written by `sha256sum`
+    #[default]
+    Gnu,
+
+    /// `SHA256 (<path>) = <hex>`, as written by `shasum --tag`
+    Bsd,
+}
+
+/// A line of a checksum manifest.
+#[derive(Debug, PartialEq)]
+pub struct ManifestEntry {
+    /// The algorithm named by the line, only set in the BSD format
+    pub algorithm: Option<HashAlgorithm>,
+
+    /// The path of the file, unescaped
+    pub path: String,
+
+    /// The expected digest, in lowercase hexadecimal
+    pub hash: String,
+}
+
+/// Format a manifest line for the file.
+/// Like coreutils, a path containing a backslash or a line break is escaped
+/// and the line starts with a backslash.
+pub fn format_line(
+    format: ManifestFormat,
+    algorithm: &HashAlgorithm,
+    path: &str,
+    hash: &str,
+) -> String {
+    let escaped = path.contains(['\\', '\n', '\r']);
+    let prefix = if escaped { "\\" } else { "" };
+    let path = if escaped {
+        escape(path)
+    } else {
+        path.to_string()
+    };
+
+    match format {
+        ManifestFormat::Gnu => format!("{prefix}{hash}  {path}"),
+        ManifestFormat::Bsd => format!("{prefix}{} ({path}) = {hash}", algorithm.bsd_tag()),
+    }
+}
+
+/// Parse a manifest line in the GNU or BSD format.
+/// Returns `None` if the line is not a valid manifest line.
+pub fn parse_line(line: &str) -> Option<ManifestEntry> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+
+    let (algorithm, path, hash) = parse_bsd(line).or_else(|| parse_gnu(line))?;
+
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let path = if escaped {
+        unescape(path)?
+    } else {
+        path.to_string()
+    };
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Some(ManifestEntry {
+        algorithm,
+        path,
+        hash: hash.to_ascii_lowercase(),
+    })
+}
+
+/// `<hex>  <path>`, or `<hex> *<path>` for the files hashed in binary mode.
+fn parse_gnu(line: &str) -> Option<(Option<HashAlgorithm>, &str, &str)> {
+    let (hash, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+
+    Some((None, path, hash))
+}
+
+/// `<TAG> (<path>) = <hex>`
+fn parse_bsd(line: &str) -> Option<(Option<HashAlgorithm>, &str, &str)> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (path, hash) = rest.rsplit_once(") = ")?;
+    let algorithm = HashAlgorithm::from_name(tag)?;
+
+    Some((Some(algorithm), path, hash))
+}
+
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(path: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[rstest]
+    #[case(ManifestFormat::Gnu, "dir/file.txt", format!("{HASH}  dir/file.txt"))]
+    #[case(ManifestFormat::Bsd, "dir/file.txt", format!("SHA256 (dir/file.txt) = {HASH}"))]
+    #[case(ManifestFormat::Gnu, "new\nline", format!("\\{HASH}  new\\nline"))]
+    fn test_format_line(
+        #[case] format: ManifestFormat,
+        #[case] path: &str,
+        #[case] expected: String,
+    ) {
+        let line = format_line(format, &HashAlgorithm::Sha2_256, path, HASH);
+
+        assert_eq!(line, expected);
+
+        let entry = parse_line(&line).unwrap();
+        assert_eq!(entry.path, path);
+        assert_eq!(entry.hash, HASH);
+    }
+
+    #[rstest]
+    #[case(format!("{HASH} *binary.bin"), None, "binary.bin")]
+    #[case(format!("SHA2-512 (a (1).txt) = {HASH}"), Some(HashAlgorithm::Sha2_512), "a (1).txt")]
+    #[case(format!("MD5 (file with  spaces) = {HASH}"), Some(HashAlgorithm::Md5), "file with  spaces")]
+    fn test_parse_line(
+        #[case] line: String,
+        #[case] algorithm: Option<HashAlgorithm>,
+        #[case] path: &str,
+    ) {
+        assert_eq!(
+            parse_line(&line),
+            Some(ManifestEntry {
+                algorithm,
+                path: path.to_string(),
+                hash: HASH.to_string(),
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("not a manifest line")]
+    #[case("zz  file.txt")]
+    #[case("SHA224 (file.txt) = abc")]
+    fn test_parse_invalid_line(#[case] line: &str) {
+        assert_eq!(parse_line(line), None);
+    }
+}
@@ -1,8 +1,16 @@
-use clap::Subcommand;
+use clap::{ArgAction, Args, Subcommand, builder};
+
+use crate::path_filter::PathFilter;
+use plan::PlanFormat;
 
 pub mod copy;
+pub mod engine;
 pub mod hash;
+pub mod journal;
+pub mod manifest;
 pub mod r#move;
+pub mod plan;
+pub mod preserve;
 pub mod remove;
 
 #[derive(Subcommand, Clone)]
@@ -23,3 +31,68 @@ pub enum FileCmd {
     #[command(about = "Hash the source path", visible_aliases = &["h"])]
     Hash(hash::Command),
 }
+
+/// A struct that holds the filter options available for the file commands.
+#[derive(Args, Clone)]
+pub struct FilterCmdOpt {
+    #[arg(
+        long,
+        action = ArgAction::Append,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Only include the files matching this glob pattern (or inside a folder matching it), relative to the source path. \
+                A pattern without '/' matches the names at any depth. Can be repeated."
+    )]
+    include: Vec<String>,
+
+    #[arg(
+        long,
+        action = ArgAction::Append,
+        value_parser = builder::NonEmptyStringValueParser::new(),
+        help = "Exclude the files and folders matching this glob pattern, relative to the source path. \
+                Excluded folders are not explored. A pattern without '/' matches the names at any depth. Can be repeated."
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Skip the files and folders ignored by the .gitignore, .ignore and .clixyignore files found in the source path. \
+                Deeper files take precedence, and .clixyignore takes precedence over .ignore, which takes precedence over .gitignore."
+    )]
+    respect_gitignore: bool,
+}
+
+/// A struct that holds the dry-run options available for the file commands.
+#[derive(Args, Clone)]
+pub struct DryRunCmdOpt {
+    #[arg(
+        long,
+        default_value = "false",
+        value_parser = builder::BoolValueParser::new(),
+        help = "Print what would be created, overwritten, skipped or deleted, with the sizes, without changing anything."
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        default_value = "list",
+        value_enum,
+        ignore_case = true,
+        help = "The format of the plan printed in dry-run mode."
+    )]
+    plan_format: PlanFormat,
+}
+
+impl FilterCmdOpt {
+    /// Build the filter from the patterns, printing an error if one of them is invalid.
+    pub fn to_filter(&self) -> Option<PathFilter> {
+        match PathFilter::new(&self.include, &self.exclude) {
+            Ok(filter) => Some(filter.with_ignore_files(self.respect_gitignore)),
+            Err(e) => {
+                eprintln!("Invalid filter pattern: {e}");
+                None
+            }
+        }
+    }
+}
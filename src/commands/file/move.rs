@@ -1,11 +1,14 @@
 use crate::commands::BaseCmdOpt;
 use crate::commands::file::{
-    copy::{OptionsTypes, copy_dirs, copy_files, verify_copy},
-    remove::{remove_dirs, remove_files},
+    DryRunCmdOpt, FilterCmdOpt,
+    copy::{OptionsTypes, copy_dirs, copy_files, plan_copy, verify_copy},
+    engine::{Verification, VerifyMode},
+    preserve::Preserve,
+    remove::{plan_remove, remove_dirs, remove_files},
 };
 use crate::{
     path_content::{IgnoreFlag, PathContent},
-    utils::{confirm_continue, round_bytes_size},
+    utils::{confirm_continue, hash::HashAlgorithm, round_bytes_size},
 };
 use clap::{Args, builder};
 use std::{
@@ -35,6 +38,12 @@ pub struct Command {
 
     #[clap(flatten)]
     pub base: BaseCmdOpt,
+
+    #[clap(flatten)]
+    filter: FilterCmdOpt,
+
+    #[clap(flatten)]
+    dry_run: DryRunCmdOpt,
 }
 
 pub fn execute(cmd: Command) {
@@ -42,6 +51,11 @@ pub fn execute(cmd: Command) {
         source,
         destination,
         base: BaseCmdOpt { workers },
+        filter,
+        dry_run: DryRunCmdOpt {
+            dry_run,
+            plan_format,
+        },
     } = cmd;
 
     if rayon::ThreadPoolBuilder::new()
@@ -63,7 +77,12 @@ pub fn execute(cmd: Command) {
     let source_path = Path::new(&source);
     let destination_path = Path::new(&destination);
 
-    let mut path_content = PathContent::new();
+    let Some(filter) = filter.to_filter() else {
+        println!("Aborting move");
+        return;
+    };
+
+    let mut path_content = PathContent::new().with_filter(filter);
 
     let into = false;
 
@@ -97,7 +116,7 @@ pub fn execute(cmd: Command) {
             eprintln!("Destination path exists and is not a folder, aborting move");
             return;
         }
-    } else if source_path.is_dir() {
+    } else if source_path.is_dir() && !dry_run {
         if std::fs::create_dir_all(destination_path).is_err() {
             eprintln!("Error creating destination path, aborting move");
             return;
@@ -105,6 +124,22 @@ pub fn execute(cmd: Command) {
         println!("Destination path created");
     } // else, the file will be moved to the destination file during the copy phase
 
+    if dry_run {
+        plan_copy(
+            &path_content,
+            source_path,
+            destination_path,
+            into,
+            &OptionsTypes::None,
+        )
+        .print(plan_format);
+
+        add_removable_source(&mut path_content, source_path);
+
+        plan_remove(&path_content, source_path).print(plan_format);
+        return;
+    }
+
     let copy_list_of_errors = Arc::new(Mutex::new(vec![]));
 
     let dirs_ok;
@@ -119,12 +154,18 @@ pub fn execute(cmd: Command) {
             destination_path,
             &copy_list_of_errors,
             into,
+            None,
         );
     }
 
     if dirs_ok && !path_content.list_of_files.is_empty() {
         let option = OptionsTypes::None;
 
+        let verification = Verification {
+            algorithm: HashAlgorithm::Sha2_256,
+            mode: VerifyMode::default(),
+        };
+
         let copied_files = copy_files(
             &path_content,
             source_path,
@@ -132,11 +173,14 @@ pub fn execute(cmd: Command) {
             &copy_list_of_errors,
             into,
             &option,
+            &Preserve::default(),
+            Some(&verification),
+            None,
         );
 
         // TODO : add a flag to skip the verification
         // if !no_verify {
-        verify_copy(&copied_files, &copy_list_of_errors);
+        verify_copy(&copied_files, &verification, &copy_list_of_errors, None);
         // }
     } else {
         println!("No files to move");
@@ -176,14 +220,7 @@ pub fn execute(cmd: Command) {
         files_ok = remove_files(&path_content, &remove_list_of_errors);
     }
 
-    // Add the source path to the list of directories to remove
-    if source_path.is_dir()
-        && !path_content
-            .list_of_dirs
-            .contains(&source_path.to_path_buf())
-    {
-        path_content.list_of_dirs.push(source_path.to_path_buf());
-    }
+    add_removable_source(&mut path_content, source_path);
 
     if files_ok && !path_content.list_of_dirs.is_empty() {
         remove_dirs(&path_content, &remove_list_of_errors, source_path);
@@ -219,3 +256,20 @@ pub fn execute(cmd: Command) {
         }
     }
 }
+
+/// Keep only the directories that can be removed once their content has been moved,
+/// and add the source path itself to them.
+fn add_removable_source(path_content: &mut PathContent, source_path: &Path) {
+    // The directories still containing filtered entries must be kept
+    path_content.drop_partial_dirs();
+
+    // Add the source path to the list of directories to remove
+    if source_path.is_dir()
+        && !path_content.partial_dirs.contains(source_path)
+        && !path_content
+            .list_of_dirs
+            .contains(&source_path.to_path_buf())
+    {
+        path_content.list_of_dirs.push(source_path.to_path_buf());
+    }
+}
@@ -0,0 +1,251 @@
+use crate::utils::round_bytes_size;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum PlanFormat {
+    #[default]
+    List,
+    Tree,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanAction {
+    Create,
+    Overwrite,
+    Skip,
+    Delete,
+}
+
+impl Display for PlanAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanAction::Create => f.pad("create"),
+            PlanAction::Overwrite => f.pad("overwrite"),
+            PlanAction::Skip => f.pad("skip"),
+            PlanAction::Delete => f.pad("delete"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PlanEntry {
+    /// What would be done with the entry
+    pub action: PlanAction,
+
+    /// The path of the entry, relative to the root of the plan (empty for the root itself)
+    pub path: PathBuf,
+
+    /// Whether the entry is a directory
+    pub is_dir: bool,
+
+    /// The size of the entry in bytes, for files
+    pub size: Option<u64>,
+}
+
+/// The list of operations a file command would do on a path, printed in dry-run mode.
+#[derive(Debug)]
+pub struct Plan {
+    /// The path the entries are relative to
+    pub root: PathBuf,
+
+    /// The entries of the plan
+    pub entries: Vec<PlanEntry>,
+}
+
+impl Plan {
+    pub fn new(root: &Path) -> Self {
+        Plan {
+            root: root.to_path_buf(),
+            entries: vec![],
+        }
+    }
+
+    /// Add an entry to the plan. The path must be under the root of the plan.
+    pub fn add(&mut self, action: PlanAction, path: &Path, is_dir: bool, size: Option<u64>) {
+        let path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+
+        self.entries.push(PlanEntry {
+            action,
+            path,
+            is_dir,
+            size,
+        });
+    }
+
+    /// Print the plan in the given format, followed by a summary.
+    pub fn print(&self, format: PlanFormat) {
+        match format {
+            PlanFormat::List => self.print_list(),
+            PlanFormat::Tree => self.print_tree(),
+        }
+
+        println!("{}", self.summary());
+    }
+
+    /// Returns a summary with the number of entries and the size for each action.
+    pub fn summary(&self) -> String {
+        let actions = [
+            PlanAction::Create,
+            PlanAction::Overwrite,
+            PlanAction::Skip,
+            PlanAction::Delete,
+        ];
+
+        let parts: Vec<String> = actions
+            .iter()
+            .filter_map(|action| {
+                let entries: Vec<&PlanEntry> = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.action == *action)
+                    .collect();
+
+                if entries.is_empty() {
+                    return None;
+                }
+
+                let size: u64 = entries.iter().filter_map(|entry| entry.size).sum();
+
+                Some(format!(
+                    "{} to {action} ({})",
+                    entries.len(),
+                    round_bytes_size(size)
+                ))
+            })
+            .collect();
+
+        if parts.is_empty() {
+            format!("Nothing to do in {}", self.root.display())
+        } else {
+            format!("Plan for {}: {}", self.root.display(), parts.join(", "))
+        }
+    }
+
+    fn print_list(&self) {
+        let mut entries: Vec<&PlanEntry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        for entry in entries {
+            println!(
+                "{:<9} {:>10}  {}",
+                entry.action,
+                entry.size.map(round_bytes_size).unwrap_or_default(),
+                self.display_path(entry)
+            );
+        }
+    }
+
+    fn print_tree(&self) {
+        let mut root = TreeNode::default();
+
+        for entry in &self.entries {
+            let mut node = &mut root;
+
+            for component in entry.path.components() {
+                node = node
+                    .children
+                    .entry(component.as_os_str().to_os_string())
+                    .or_default();
+            }
+
+            node.entry = Some(entry);
+        }
+
+        let root_label = match root.entry {
+            Some(entry) => format!("{} {}", self.root.display(), entry_label(entry)),
+            None => self.root.display().to_string(),
+        };
+
+        println!("{root_label}");
+        root.print_children("");
+    }
+
+    fn display_path(&self, entry: &PlanEntry) -> String {
+        let path = if entry.path.as_os_str().is_empty() {
+            self.root.clone()
+        } else {
+            self.root.join(&entry.path)
+        };
+
+        if entry.is_dir {
+            format!("{}/", path.display())
+        } else {
+            path.display().to_string()
+        }
+    }
+}
+
+#[derive(Default)]
+struct TreeNode<'a> {
+    entry: Option<&'a PlanEntry>,
+    children: BTreeMap<OsString, TreeNode<'a>>,
+}
+
+impl TreeNode<'_> {
+    fn print_children(&self, prefix: &str) {
+        let count = self.children.len();
+
+        for (position, (name, child)) in self.children.iter().enumerate() {
+            let is_last = position + 1 == count;
+            let branch = if is_last { "└── " } else { "├── " };
+
+            let label = match child.entry {
+                Some(entry) => format!("{} {}", name.to_string_lossy(), entry_label(entry)),
+                None => name.to_string_lossy().to_string(),
+            };
+
+            println!("{prefix}{branch}{label}");
+
+            let child_prefix = format!("{prefix}{}", if is_last { "    " } else { "│   " });
+            child.print_children(&child_prefix);
+        }
+    }
+}
+
+fn entry_label(entry: &PlanEntry) -> String {
+    match entry.size {
+        Some(size) => format!("[{}, {}]", entry.action, round_bytes_size(size)),
+        None => format!("[{}]", entry.action),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_summary() {
+        let mut plan = Plan::new(Path::new("destination"));
+
+        plan.add(PlanAction::Create, Path::new("destination/dir"), true, None);
+        plan.add(
+            PlanAction::Create,
+            Path::new("destination/dir/a.txt"),
+            false,
+            Some(1024),
+        );
+        plan.add(
+            PlanAction::Overwrite,
+            Path::new("destination/b.txt"),
+            false,
+            Some(10),
+        );
+
+        assert_eq!(plan.entries[1].path, Path::new("dir/a.txt"));
+        assert_eq!(
+            plan.summary(),
+            "Plan for destination: 2 to create (1.00 KB), 1 to overwrite (10 B)"
+        );
+    }
+
+    #[test]
+    fn test_plan_summary_empty() {
+        let plan = Plan::new(Path::new("destination"));
+
+        assert_eq!(plan.summary(), "Nothing to do in destination");
+    }
+}
@@ -0,0 +1,226 @@
+use clap::ValueEnum;
+use std::fs::{File, FileTimes, Metadata, Permissions, set_permissions};
+use std::io::Result;
+use std::path::Path;
+
+#[derive(Debug, ValueEnum, Clone, PartialEq)]
+pub enum PreserveAttribute {
+    Mode,
+    Timestamps,
+    Ownership,
+    All,
+}
+
+/// The attributes of the source entries to apply to the copied entries.
+#[derive(Debug, Default, Clone)]
+pub struct Preserve {
+    /// Whether the permissions (mode bits) are preserved
+    pub mode: bool,
+
+    /// Whether the access and modification times are preserved
+    pub timestamps: bool,
+
+    /// Whether the owner and group are preserved (Unix only)
+    pub ownership: bool,
+}
+
+impl Preserve {
+    pub fn from_attributes(attributes: &[PreserveAttribute]) -> Self {
+        let mut preserve = Preserve::default();
+
+        for attribute in attributes {
+            match attribute {
+                PreserveAttribute::Mode => preserve.mode = true,
+                PreserveAttribute::Timestamps => preserve.timestamps = true,
+                PreserveAttribute::Ownership => preserve.ownership = true,
+                PreserveAttribute::All => {
+                    preserve.mode = true;
+                    preserve.timestamps = true;
+                    preserve.ownership = true;
+                }
+            }
+        }
+
+        preserve
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.mode && !self.timestamps && !self.ownership
+    }
+
+    /// Apply the preserved attributes of the source entry to the destination entry.
+    /// The ownership is applied first because changing it can clear some mode bits,
+    /// and the mode is applied last so a read-only mode doesn't prevent the other changes.
+    pub fn apply(&self, source: &Path, destination: &Path) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let metadata = source.metadata()?;
+
+        let owner_kept = !self.ownership || apply_ownership(&metadata, destination)?;
+
+        if self.timestamps {
+            let times = FileTimes::new()
+                .set_accessed(metadata.accessed()?)
+                .set_modified(metadata.modified()?);
+
+            open_for_times(destination)?.set_times(times)?;
+        }
+
+        if self.mode {
+            set_permissions(destination, mode_permissions(&metadata, owner_kept))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Give the destination the owner and group of the source.
+/// Returns false if the process isn't allowed to, which is not an error, like with `cp -p`.
+#[cfg(unix)]
+fn apply_ownership(metadata: &Metadata, destination: &Path) -> Result<bool> {
+    use std::io::ErrorKind;
+    use std::os::unix::fs::{MetadataExt, chown};
+
+    match chown(destination, Some(metadata.uid()), Some(metadata.gid())) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_ownership(_metadata: &Metadata, _destination: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+/// Returns the permissions of the source to apply to the destination.
+/// If the owner couldn't be kept, the setuid and setgid bits are cleared, like with `cp -p`,
+/// so they don't apply to another owner.
+#[cfg(unix)]
+fn mode_permissions(metadata: &Metadata, owner_kept: bool) -> Permissions {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+
+    if owner_kept {
+        Permissions::from_mode(mode)
+    } else {
+        Permissions::from_mode(mode & !0o6000)
+    }
+}
+
+#[cfg(not(unix))]
+fn mode_permissions(metadata: &Metadata, _owner_kept: bool) -> Permissions {
+    metadata.permissions()
+}
+
+/// Open a file or a directory to change its times.
+#[cfg(not(windows))]
+fn open_for_times(path: &Path) -> Result<File> {
+    File::open(path)
+}
+
+/// Open a file or a directory to change its times.
+/// On Windows, changing the times needs the write attributes access,
+/// and a directory can only be opened with the backup semantics flag.
+#[cfg(windows)]
+fn open_for_times(path: &Path) -> Result<File> {
+    use std::fs::OpenOptions;
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+
+    OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_preserve_timestamps_and_mode() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source.txt");
+        let destination = dir.path().join("destination.txt");
+
+        File::create(&source).unwrap();
+        File::create(&destination).unwrap();
+
+        let modified = SystemTime::now() - Duration::from_secs(3600);
+        open_for_times(&source)
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+
+        let mut permissions = source.metadata().unwrap().permissions();
+        permissions.set_readonly(true);
+        set_permissions(&source, permissions).unwrap();
+
+        Preserve::from_attributes(&[PreserveAttribute::Timestamps, PreserveAttribute::Mode])
+            .apply(&source, &destination)
+            .unwrap();
+
+        let metadata = destination.metadata().unwrap();
+
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert!(metadata.permissions().readonly());
+    }
+
+    #[test]
+    fn test_preserve_dir_timestamps() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        let destination = dir.path().join("destination");
+
+        std::fs::create_dir(&source).unwrap();
+        std::fs::create_dir(&destination).unwrap();
+
+        let modified = SystemTime::now() - Duration::from_secs(3600);
+        open_for_times(&source)
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+
+        Preserve::from_attributes(&[PreserveAttribute::Timestamps])
+            .apply(&source, &destination)
+            .unwrap();
+
+        assert_eq!(
+            destination.metadata().unwrap().modified().unwrap(),
+            modified
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mode_permissions_owner_not_kept() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+
+        File::create(&source).unwrap();
+        set_permissions(&source, Permissions::from_mode(0o4755)).unwrap();
+
+        let metadata = source.metadata().unwrap();
+
+        assert_eq!(mode_permissions(&metadata, true).mode() & 0o7777, 0o4755);
+        assert_eq!(mode_permissions(&metadata, false).mode() & 0o7777, 0o755);
+    }
+
+    #[test]
+    fn test_preserve_all() {
+        let preserve = Preserve::from_attributes(&[PreserveAttribute::All]);
+
+        assert!(preserve.mode && preserve.timestamps && preserve.ownership);
+        assert!(Preserve::from_attributes(&[]).is_empty());
+    }
+}
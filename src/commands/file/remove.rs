@@ -1,8 +1,10 @@
 use crate::commands::BaseCmdOpt;
-use crate::path_content::{IgnoreFlag, PathContent};
+use crate::commands::file::plan::{Plan, PlanAction};
+use crate::commands::file::{DryRunCmdOpt, FilterCmdOpt};
+use crate::path_content::{IgnoreFlag, PathContent, SymlinkPolicy};
 use crate::progress_bar_helper;
 use crate::utils::{add_error, confirm_continue, round_bytes_size};
-use clap::{ArgAction, Args, builder};
+use clap::{ArgAction, Args, ValueEnum, builder};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::{
     collections::BTreeMap,
@@ -25,6 +27,12 @@ pub struct Command {
     #[clap(flatten)]
     base: BaseCmdOpt,
 
+    #[clap(flatten)]
+    filter: FilterCmdOpt,
+
+    #[clap(flatten)]
+    dry_run: DryRunCmdOpt,
+
     #[arg(
         long,
         default_value = "false",
@@ -50,15 +58,50 @@ pub struct Command {
         help = "Remove only the content of the source path, not the source path itself."
     )]
     content_only: bool,
+
+    #[arg(
+        long,
+        default_value = "copy-link",
+        value_enum,
+        ignore_case = true,
+        help = "How to handle symbolic links. Following links is not allowed, it would remove entries outside of the source path."
+    )]
+    symlinks: RemoveSymlinkPolicy,
+}
+
+/// The symbolic link policies allowed when removing.
+#[derive(Debug, Default, ValueEnum, Clone, Copy, PartialEq)]
+pub enum RemoveSymlinkPolicy {
+    /// Remove the links themselves, never the entries they point to
+    #[default]
+    CopyLink,
+
+    /// Keep the links, and the folders containing them
+    Skip,
+}
+
+impl From<RemoveSymlinkPolicy> for SymlinkPolicy {
+    fn from(policy: RemoveSymlinkPolicy) -> Self {
+        match policy {
+            RemoveSymlinkPolicy::CopyLink => SymlinkPolicy::CopyLink,
+            RemoveSymlinkPolicy::Skip => SymlinkPolicy::Skip,
+        }
+    }
 }
 
 pub fn execute(cmd: Command) {
     let Command {
         source,
         base: BaseCmdOpt { workers },
+        filter,
+        dry_run: DryRunCmdOpt {
+            dry_run,
+            plan_format,
+        },
         only_files,
         yes,
         content_only,
+        symlinks,
     } = cmd;
 
     if rayon::ThreadPoolBuilder::new()
@@ -79,7 +122,14 @@ pub fn execute(cmd: Command) {
 
     let source_path = Path::new(&source);
 
-    let mut path_content = PathContent::new();
+    let Some(filter) = filter.to_filter() else {
+        println!("Aborting remove");
+        return;
+    };
+
+    let mut path_content = PathContent::new()
+        .with_symlinks(symlinks.into())
+        .with_filter(filter);
 
     let ignore_flag = if only_files {
         IgnoreFlag::Directories
@@ -95,11 +145,19 @@ pub fn execute(cmd: Command) {
         return;
     }
 
+    // The directories still containing filtered entries must be kept
+    path_content.drop_partial_dirs();
+
     if path_content.entries == 0 {
         println!("Source path is empty, nothing to remove");
         return;
     }
 
+    if dry_run {
+        plan_remove(&path_content, source_path).print(plan_format);
+        return;
+    }
+
     if !yes {
         println!(
             "Removing {} files and {} directories from {} ({} entries, {})",
@@ -160,7 +218,34 @@ pub fn execute(cmd: Command) {
     }
 }
 
+/// Compute what the remove would do, without changing anything.
+pub fn plan_remove(path_content: &PathContent, source_path: &Path) -> Plan {
+    let mut plan = Plan::new(source_path);
+
+    plan_removals(&mut plan, path_content);
+
+    plan
+}
+
+/// Add the removal of all the entries of the path content to the plan.
+pub fn plan_removals(plan: &mut Plan, path_content: &PathContent) {
+    for dir in &path_content.list_of_dirs {
+        plan.add(PlanAction::Delete, dir, true, None);
+    }
+
+    for file in &path_content.list_of_files {
+        let size = if path_content.is_link_entry(file) {
+            None
+        } else {
+            file.metadata().ok().map(|metadata| metadata.len())
+        };
+
+        plan.add(PlanAction::Delete, file, false, size);
+    }
+}
+
 /// Remove all files in the path content.
+/// Symbolic links are removed themselves, never the entries they point to.
 /// Returns true if all files were removed successfully, false otherwise.
 /// Note: because of the parallel processing, a flag protected by a mutex is used to track the status.
 /// At the end of the process, the mutex is unwrapped to get the final status. If an error with the mutex occurs,
@@ -174,7 +259,14 @@ pub fn remove_files(path_content: &PathContent, list_of_errors: &Arc<Mutex<Vec<S
     let is_ok = Mutex::new(true);
 
     path_content.list_of_files.par_iter().for_each(|item| {
-        if remove_file(item).is_err() {
+        // On Windows, a link to a directory is removed like a directory
+        let result = if cfg!(windows) && path_content.is_link_entry(item) && item.is_dir() {
+            remove_dir(item)
+        } else {
+            remove_file(item)
+        };
+
+        if result.is_err() {
             add_error(list_of_errors, format!("Error removing file {item:?}"));
             if let Ok(mut is_ok) = is_ok.lock() {
                 *is_ok = false;
@@ -0,0 +1,105 @@
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The ignore files read in each directory, by decreasing precedence.
+/// `.clixyignore` overrides `.ignore`, which overrides `.gitignore`.
+pub const IGNORE_FILES: [&str; 3] = [".clixyignore", ".ignore", ".gitignore"];
+
+/// The ignore rules applying to a directory: the rules of its own ignore files,
+/// then the rules of its parent directories.
+#[derive(Debug, Default)]
+pub struct IgnoreLevel {
+    /// The rules of the parent directory, if any
+    parent: Option<Arc<IgnoreLevel>>,
+
+    /// The rules of the ignore files of the directory, by decreasing precedence
+    matchers: Vec<Gitignore>,
+}
+
+impl IgnoreLevel {
+    /// Read the ignore files of the directory and returns the rules applying to its content.
+    /// If the directory has no ignore file, the rules of the parent are returned as they are.
+    /// Invalid lines in the ignore files are skipped, like git does.
+    pub fn for_dir(dir: &Path, parent: Option<&Arc<IgnoreLevel>>) -> Arc<IgnoreLevel> {
+        let mut matchers = vec![];
+
+        for name in IGNORE_FILES {
+            let ignore_file = dir.join(name);
+
+            if !ignore_file.is_file() {
+                continue;
+            }
+
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add(ignore_file);
+
+            if let Ok(matcher) = builder.build() {
+                matchers.push(matcher);
+            }
+        }
+
+        match parent {
+            Some(parent) if matchers.is_empty() => Arc::clone(parent),
+            _ => Arc::new(IgnoreLevel {
+                parent: parent.map(Arc::clone),
+                matchers,
+            }),
+        }
+    }
+
+    /// Returns true if the entry is ignored.
+    /// The deepest directories have the precedence, and a negated rule (`!pattern`) can
+    /// include again an entry ignored by a rule with a lower precedence.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut level = Some(self);
+
+        while let Some(current) = level {
+            for matcher in &current.matchers {
+                match matcher.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+
+            level = current.parent.as_deref();
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir, write};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ignore_precedence() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let sub = root.join("sub");
+        create_dir(&sub).unwrap();
+
+        write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        write(root.join(".ignore"), "!keep.log\n").unwrap();
+        write(sub.join(".gitignore"), "!debug.log\n").unwrap();
+        write(sub.join(".clixyignore"), "secret.txt\n").unwrap();
+
+        let root_level = IgnoreLevel::for_dir(root, None);
+        let sub_level = IgnoreLevel::for_dir(&sub, Some(&root_level));
+
+        assert!(root_level.is_ignored(&root.join("target"), true));
+        assert!(!root_level.is_ignored(&root.join("target"), false));
+        assert!(root_level.is_ignored(&root.join("app.log"), false));
+        assert!(!root_level.is_ignored(&root.join("keep.log"), false));
+
+        assert!(sub_level.is_ignored(&sub.join("app.log"), false));
+        assert!(!sub_level.is_ignored(&sub.join("debug.log"), false));
+        assert!(sub_level.is_ignored(&sub.join("secret.txt"), false));
+        assert!(!sub_level.is_ignored(&sub.join("main.rs"), false));
+    }
+}
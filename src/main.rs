@@ -1,6 +1,8 @@
 #[allow(clippy::too_many_lines)]
 mod commands;
+mod ignore_files;
 mod path_content;
+mod path_filter;
 mod progress_bar_helper;
 mod utils;
 
@@ -8,7 +10,7 @@ use clap::{Parser, Subcommand, crate_authors, crate_description, crate_version};
 
 use commands::{
     DescribeCmd,
-    crypto::CryptoCmd,
+    crypto::{CryptoCmd, otp::OtpCmd, password::PasswordCmd},
     file::{FileCmd, copy, r#move, remove},
     random::RandomCmd,
 };
@@ -81,9 +83,49 @@ fn main() {
             CryptoCmd::Hash(command) => {
                 command.execute();
             }
+            CryptoCmd::Encode(command) => {
+                command.encode();
+            }
+            CryptoCmd::Decode(command) => {
+                command.decode();
+            }
+            CryptoCmd::Password(command) => match command {
+                PasswordCmd::Hash(command) => {
+                    command.execute();
+                }
+                PasswordCmd::Verify(command) => {
+                    command.execute();
+                }
+            },
+            CryptoCmd::Otp(command) => match command {
+                OtpCmd::Generate(command) => {
+                    command.execute();
+                }
+                OtpCmd::Verify(command) => {
+                    command.execute();
+                }
+            },
+            CryptoCmd::Keygen(command) => {
+                command.execute();
+            }
+            CryptoCmd::Sign(command) => {
+                command.execute();
+            }
+            CryptoCmd::Verify(command) => {
+                command.execute();
+            }
             CryptoCmd::Caesar(command) => {
                 command.execute();
             }
+            CryptoCmd::Classic(command) => {
+                command.execute();
+            }
+            CryptoCmd::Encrypt(command) => {
+                command.encrypt();
+            }
+            CryptoCmd::Decrypt(command) => {
+                command.decrypt();
+            }
         },
         #[cfg(feature = "file")]
         Commands::File(command) => match command {
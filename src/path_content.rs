@@ -1,11 +1,16 @@
+use crate::ignore_files::IgnoreLevel;
+use crate::path_filter::PathFilter;
 use crate::progress_bar_helper;
+use clap::ValueEnum;
 use indicatif::ProgressBar;
 use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::collections::{HashMap, HashSet};
 use std::fs::read_dir;
-use std::io::{Error, ErrorKind, Result};
+use std::io::{Error, Result};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PathContent {
     /// The number of entries in the path
     pub entries: u64,
@@ -16,13 +21,37 @@ pub struct PathContent {
     /// A list of directories in the path
     pub list_of_dirs: Vec<PathBuf>,
 
-    /// A list of files in the path
+    /// A list of files in the path.
+    /// Symbolic links that are not followed are listed here too, whatever they point to.
     pub list_of_files: Vec<PathBuf>,
 
+    /// How symbolic links are handled during the indexing
+    pub symlinks: SymlinkPolicy,
+
+    /// The include and exclude patterns applied during the indexing
+    pub filter: PathFilter,
+
+    /// The directories containing entries dropped by the filter or skipped links.
+    /// They are only partially indexed, so they can't be removed as a whole.
+    pub partial_dirs: HashSet<PathBuf>,
+
     // Indicates if the index has been created or not
     indexed: bool,
 }
 
+#[derive(Debug, Default, ValueEnum, Clone, Copy, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Index the links themselves, so they are copied or removed as links
+    #[default]
+    CopyLink,
+
+    /// Index the entries the links point to, as if they were regular entries
+    Follow,
+
+    /// Don't index the links at all
+    Skip,
+}
+
 #[derive(Debug, Default)]
 pub enum IgnoreFlag {
     Files,
@@ -38,16 +67,34 @@ impl PathContent {
             size: 0,
             list_of_dirs: vec![],
             list_of_files: vec![],
+            symlinks: SymlinkPolicy::default(),
+            filter: PathFilter::default(),
+            partial_dirs: HashSet::new(),
             indexed: false,
         }
     }
 
+    /// Set the policy used for symbolic links during the indexing.
+    pub fn with_symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Set the filter applied to the entries during the indexing.
+    pub fn with_filter(mut self, filter: PathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Returns true if the entry must be handled as a link and not as the entry it points to.
+    /// This is the case of the indexed links when they are not followed, and of broken links.
+    pub fn is_link_entry(&self, path: &Path) -> bool {
+        path.is_symlink() && (self.symlinks != SymlinkPolicy::Follow || !path.exists())
+    }
+
     pub fn index_entries(&mut self, path: &Path, into: bool, ignore: &IgnoreFlag) -> Result<()> {
         if self.indexed {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "The path content has already been indexed",
-            ));
+            return Err(Error::other("The path content has already been indexed"));
         }
 
         self.indexed = true;
@@ -56,6 +103,20 @@ impl PathContent {
 
         pb.set_message(format!("Indexing entries: {}", self.entries));
 
+        // Directories already explored, to avoid loops when following symbolic links
+        let mut visited_dirs = HashSet::new();
+
+        if self.symlinks == SymlinkPolicy::Follow && !into && path.is_dir() {
+            visited_dirs.insert(dir_identity(path)?);
+        }
+
+        // The ignore rules applying to the content of each explored directory
+        let mut ignore_levels: HashMap<PathBuf, Arc<IgnoreLevel>> = HashMap::new();
+
+        if self.filter.respect_ignore_files && !into && path.is_dir() {
+            ignore_levels.insert(path.to_path_buf(), IgnoreLevel::for_dir(path, None));
+        }
+
         let mut list_to_explore = if into {
             // The source path will be copied directly into the destination path
             vec![path.to_path_buf()]
@@ -71,7 +132,49 @@ impl PathContent {
         };
 
         while let Some(item) = list_to_explore.pop() {
-            if item.is_dir() {
+            let relative_path = item.strip_prefix(path).unwrap_or(&item);
+
+            // The source path itself is never filtered
+            if !relative_path.as_os_str().is_empty() {
+                let is_file = self.is_link_entry(&item) || !item.is_dir();
+
+                let is_ignored = item
+                    .parent()
+                    .and_then(|parent| ignore_levels.get(parent))
+                    .is_some_and(|level| level.is_ignored(&item, !is_file));
+
+                if is_ignored
+                    || self.filter.is_excluded(relative_path)
+                    || (is_file && !self.filter.is_included(relative_path))
+                {
+                    // Excluded directories are not explored at all
+                    self.mark_partial_parents(path, &item);
+                    continue;
+                }
+            }
+
+            if self.is_link_entry(&item) {
+                if self.symlinks == SymlinkPolicy::Skip {
+                    // The parents still contain the link, they can't be removed as a whole
+                    self.mark_partial_parents(path, &item);
+                    continue;
+                }
+
+                if let IgnoreFlag::Files = ignore {
+                    // Links are indexed as files
+                    continue;
+                }
+
+                self.list_of_files.push(item);
+                self.increment_entries(&pb);
+            } else if item.is_dir() {
+                if self.symlinks == SymlinkPolicy::Follow
+                    && !visited_dirs.insert(dir_identity(&item)?)
+                {
+                    // Already explored through another path, this is a loop or a duplicate
+                    continue;
+                }
+
                 if let IgnoreFlag::Directories = ignore {
                     // Do not index directories
                     // Don't call continue here because we need to explore the directory content
@@ -80,6 +183,12 @@ impl PathContent {
                     self.increment_entries(&pb);
                 }
 
+                if self.filter.respect_ignore_files {
+                    let parent_level = item.parent().and_then(|parent| ignore_levels.get(parent));
+                    let level = IgnoreLevel::for_dir(&item, parent_level);
+                    ignore_levels.insert(item.clone(), level);
+                }
+
                 let all = read_dir(item)?
                     .all(|entry_result| {
                         match entry_result {
@@ -92,10 +201,7 @@ impl PathContent {
                     });
 
                 if !all {
-                    return Err(Error::new(
-                        ErrorKind::Other,
-                        "Error reading directory content",
-                    ));
+                    return Err(Error::other("Error reading directory content"));
                 }
             } else if item.is_file() {
                 if let IgnoreFlag::Files = ignore {
@@ -108,27 +214,121 @@ impl PathContent {
                 if let Ok(metadata) = item.metadata() {
                     self.size += metadata.len();
                 } else {
-                    return Err(Error::new(ErrorKind::Other, "Error reading file metadata"));
+                    return Err(Error::other("Error reading file metadata"));
                 }
 
                 self.list_of_files.push(item);
                 self.increment_entries(&pb);
             } else {
-                return Err(Error::new(ErrorKind::Other, "Error processing source path"));
+                return Err(Error::other("Error processing source path"));
             }
         }
 
+        if self.filter.has_includes() && !matches!(ignore, IgnoreFlag::Files) {
+            self.drop_dirs_without_includes(path);
+        }
+
+        // Sort the lists so the index is the same from one run to another
+        self.list_of_dirs.sort();
+        self.list_of_files.sort();
+
         pb.finish_with_message(format!("Indexed entries: {}", self.entries));
 
         Ok(())
     }
 
+    /// Keep only the entries for which the predicate returns true.
+    /// The predicate gets the path of the entry and whether it is a directory.
+    /// The number of entries and the size are updated.
+    pub fn retain_entries(&mut self, keep: impl Fn(&Path, bool) -> bool) {
+        self.list_of_dirs.retain(|dir| keep(dir, true));
+        self.list_of_files.retain(|file| keep(file, false));
+
+        self.entries = (self.list_of_dirs.len() + self.list_of_files.len()) as u64;
+        self.size = self
+            .list_of_files
+            .iter()
+            .filter(|file| !self.is_link_entry(file))
+            .filter_map(|file| file.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+    }
+
+    /// Remove from the list of directories the ones containing entries dropped by the filter,
+    /// so only the directories that are indexed as a whole remain (to remove them for example).
+    pub fn drop_partial_dirs(&mut self) {
+        let count = self.list_of_dirs.len();
+
+        self.list_of_dirs
+            .retain(|dir| !self.partial_dirs.contains(dir));
+
+        self.entries -= (count - self.list_of_dirs.len()) as u64;
+    }
+
+    /// Mark all the parent directories of the dropped entry, up to the indexed path, as partial.
+    fn mark_partial_parents(&mut self, path: &Path, item: &Path) {
+        for parent in item.ancestors().skip(1) {
+            if !parent.starts_with(path) || !self.partial_dirs.insert(parent.to_path_buf()) {
+                // Out of the indexed path, or the parents are already marked
+                break;
+            }
+        }
+    }
+
+    /// Keep only the directories leading to an included file, or included themselves,
+    /// so the directories with no included content are not indexed.
+    fn drop_dirs_without_includes(&mut self, path: &Path) {
+        let mut needed_dirs = HashSet::new();
+
+        for file in &self.list_of_files {
+            for parent in file.ancestors().skip(1) {
+                if !parent.starts_with(path) || !needed_dirs.insert(parent.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+
+        let count = self.list_of_dirs.len();
+
+        self.list_of_dirs.retain(|dir| {
+            needed_dirs.contains(dir)
+                || dir
+                    .strip_prefix(path)
+                    .is_ok_and(|relative_path| self.filter.is_included(relative_path))
+        });
+
+        self.entries -= (count - self.list_of_dirs.len()) as u64;
+    }
+
     fn increment_entries(&mut self, pb: &ProgressBar) {
         self.entries += 1;
         pb.set_message(format!("Indexing entries: {}", self.entries));
     }
 }
 
+/// An identifier of a directory that is the same whatever the path used to reach it.
+#[cfg(unix)]
+type DirIdentity = (u64, u64);
+
+#[cfg(not(unix))]
+type DirIdentity = PathBuf;
+
+/// Returns the identity of a directory, following symbolic links.
+/// On Unix, this is the device and inode numbers. Elsewhere, the canonical path is used.
+#[cfg(unix)]
+fn dir_identity(path: &Path) -> Result<DirIdentity> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = path.metadata()?;
+
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_identity(path: &Path) -> Result<DirIdentity> {
+    path.canonicalize()
+}
+
 #[test]
 fn test_index_entries_file() {
     let mut path_content = PathContent::new();
@@ -167,3 +367,110 @@ fn test_index_entries_ignore_dirs() {
     assert_eq!(path_content.list_of_files.len(), 1);
     assert_eq!(path_content.list_of_dirs.len(), 0);
 }
+
+/// Create a tree with a file, a link to this file and a link to the root (a loop).
+#[cfg(all(unix, test))]
+fn create_symlink_tree(root: &Path) {
+    std::fs::create_dir(root.join("dir")).unwrap();
+    std::fs::File::create(root.join("dir/file.txt")).unwrap();
+    std::os::unix::fs::symlink("file.txt", root.join("dir/link.txt")).unwrap();
+    std::os::unix::fs::symlink("..", root.join("dir/loop")).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_index_entries_copy_link() {
+    let dir = tempfile::tempdir().unwrap();
+    create_symlink_tree(dir.path());
+
+    let mut path_content = PathContent::new();
+    path_content
+        .index_entries(dir.path(), false, &IgnoreFlag::None)
+        .unwrap();
+
+    assert_eq!(path_content.list_of_dirs.len(), 1);
+    assert_eq!(path_content.list_of_files.len(), 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_index_entries_skip() {
+    let dir = tempfile::tempdir().unwrap();
+    create_symlink_tree(dir.path());
+
+    let mut path_content = PathContent::new().with_symlinks(SymlinkPolicy::Skip);
+    path_content
+        .index_entries(dir.path(), false, &IgnoreFlag::None)
+        .unwrap();
+
+    assert_eq!(path_content.list_of_dirs.len(), 1);
+    assert_eq!(path_content.list_of_files.len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_index_entries_follow_loop() {
+    let dir = tempfile::tempdir().unwrap();
+    create_symlink_tree(dir.path());
+
+    let mut path_content = PathContent::new().with_symlinks(SymlinkPolicy::Follow);
+    path_content
+        .index_entries(dir.path(), false, &IgnoreFlag::None)
+        .unwrap();
+
+    // The loop is not explored, the link to the file is indexed as a regular file
+    assert_eq!(path_content.list_of_dirs.len(), 1);
+    assert_eq!(path_content.list_of_files.len(), 2);
+    assert!(!path_content.is_link_entry(&dir.path().join("dir/link.txt")));
+}
+
+#[test]
+fn test_index_entries_filter() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+    std::fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+    std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+    std::fs::File::create(dir.path().join("src/main.rs")).unwrap();
+    std::fs::File::create(dir.path().join("src/nested/lib.rs")).unwrap();
+    std::fs::File::create(dir.path().join("target/debug/main.rs")).unwrap();
+    std::fs::File::create(dir.path().join("docs/index.md")).unwrap();
+
+    let filter = PathFilter::new(&["*.rs".to_string()], &["target".to_string()]).unwrap();
+
+    let mut path_content = PathContent::new().with_filter(filter);
+
+    path_content
+        .index_entries(dir.path(), false, &IgnoreFlag::None)
+        .unwrap();
+
+    assert_eq!(path_content.list_of_files.len(), 2);
+    // "docs" has no included file and "target" is excluded
+    assert_eq!(path_content.list_of_dirs.len(), 2);
+    assert_eq!(path_content.entries, 4);
+
+    assert!(path_content.partial_dirs.contains(dir.path()));
+    assert!(!path_content.partial_dirs.contains(&dir.path().join("src")));
+}
+
+#[test]
+fn test_index_entries_respect_ignore_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+    std::fs::write(dir.path().join("src/.clixyignore"), "*.tmp\n").unwrap();
+    std::fs::File::create(dir.path().join("target/debug/clixy")).unwrap();
+    std::fs::File::create(dir.path().join("src/main.rs")).unwrap();
+    std::fs::File::create(dir.path().join("src/main.tmp")).unwrap();
+
+    let mut path_content =
+        PathContent::new().with_filter(PathFilter::default().with_ignore_files(true));
+
+    path_content
+        .index_entries(dir.path(), false, &IgnoreFlag::None)
+        .unwrap();
+
+    // The ignore files themselves are indexed
+    assert_eq!(path_content.list_of_files.len(), 3);
+    assert_eq!(path_content.list_of_dirs.len(), 1);
+}
@@ -0,0 +1,146 @@
+use globset::{Error, GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// A filter of the entries of a path, based on include and exclude glob patterns
+/// and optionally on the ignore files found in the path (see `IgnoreLevel`).
+/// The patterns are matched against the paths relative to the indexed path.
+/// A pattern without a `/` is matched against the name of the entries, at any depth,
+/// while a pattern with a `/` is matched against the whole relative path.
+#[derive(Debug, Default, Clone)]
+pub struct PathFilter {
+    /// If set, only the files matching these patterns (or inside a directory matching them) are kept
+    include: Option<Patterns>,
+
+    /// The entries matching these patterns are dropped, directories are not explored
+    exclude: Option<Patterns>,
+
+    /// Whether the `.gitignore`, `.ignore` and `.clixyignore` files are applied
+    pub respect_ignore_files: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Patterns {
+    /// The patterns matched against the name of the entries
+    names: GlobSet,
+
+    /// The patterns matched against the relative path of the entries
+    paths: GlobSet,
+}
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Result<Option<Self>, Error> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            if pattern.contains('/') {
+                let pattern = pattern.trim_start_matches('/');
+                paths.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+            } else {
+                names.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+            }
+        }
+
+        Ok(Some(Patterns {
+            names: names.build()?,
+            paths: paths.build()?,
+        }))
+    }
+
+    fn is_match(&self, relative_path: &Path) -> bool {
+        self.paths.is_match(relative_path)
+            || relative_path
+                .file_name()
+                .is_some_and(|name| self.names.is_match(name))
+    }
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, Error> {
+        Ok(PathFilter {
+            include: Patterns::new(include)?,
+            exclude: Patterns::new(exclude)?,
+            respect_ignore_files: false,
+        })
+    }
+
+    /// Set whether the ignore files found in the path are applied.
+    pub fn with_ignore_files(mut self, respect_ignore_files: bool) -> Self {
+        self.respect_ignore_files = respect_ignore_files;
+        self
+    }
+
+    /// Returns true if there are include patterns.
+    pub fn has_includes(&self) -> bool {
+        self.include.is_some()
+    }
+
+    /// Returns true if the entry must be dropped, whatever its type.
+    pub fn is_excluded(&self, relative_path: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(relative_path))
+    }
+
+    /// Returns true if the entry, or one of its parent directories, matches the include patterns.
+    /// Always true if there are no include patterns.
+    pub fn is_included(&self, relative_path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| {
+            relative_path
+                .ancestors()
+                .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                .any(|ancestor| include.is_match(ancestor))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("target", true)]
+    #[case("sub/target", true)]
+    #[case("src/main.rs", false)]
+    #[case("docs/build", true)]
+    #[case("docs/index.html", false)]
+    #[case("app.log", true)]
+    fn test_is_excluded(#[case] path: &str, #[case] expected: bool) {
+        let filter = PathFilter::new(
+            &[],
+            &[
+                "target".to_string(),
+                "docs/build".to_string(),
+                "*.log".to_string(),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(filter.is_excluded(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("src/main.rs", true)]
+    #[case("README.md", false)]
+    #[case("docs/guide/index.html", true)]
+    #[case("other/docs.txt", false)]
+    fn test_is_included(#[case] path: &str, #[case] expected: bool) {
+        let filter = PathFilter::new(&["*.rs".to_string(), "/docs".to_string()], &[]).unwrap();
+
+        assert_eq!(filter.is_included(Path::new(path)), expected);
+    }
+
+    #[test]
+    fn test_no_patterns() {
+        let filter = PathFilter::default();
+
+        assert!(!filter.has_includes());
+        assert!(filter.is_included(Path::new("file.txt")));
+        assert!(!filter.is_excluded(Path::new("file.txt")));
+    }
+}
@@ -0,0 +1,102 @@
+//! Adapters exposing the hashes that don't come from the `RustCrypto` crates through the `digest`
+//! traits, so every `HashAlgorithm` can be used with the same incremental hasher.
+//! The integer digests are written in big-endian, like `xxhsum` and `crc32c` tools print them.
+
+use digest::consts::{U4, U8, U16, U32};
+use digest::{FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+use xxhash_rust::xxh3::Xxh3;
+
+/// BLAKE3 with a 256 bits output.
+#[derive(Clone, Default)]
+pub struct Blake3(pub blake3::Hasher);
+
+impl Blake3 {
+    fn digest_bytes(&self) -> [u8; 32] {
+        *self.0.finalize().as_bytes()
+    }
+}
+
+/// XXH3 with a 64 bits output.
+#[derive(Clone, Default)]
+pub struct Xxh3_64(Xxh3);
+
+impl Xxh3_64 {
+    fn digest_bytes(&self) -> [u8; 8] {
+        self.0.digest().to_be_bytes()
+    }
+}
+
+/// XXH3 with a 128 bits output.
+#[derive(Clone, Default)]
+pub struct Xxh3_128(Xxh3);
+
+impl Xxh3_128 {
+    fn digest_bytes(&self) -> [u8; 16] {
+        self.0.digest128().to_be_bytes()
+    }
+}
+
+/// CRC-32C (Castagnoli).
+#[derive(Clone, Default)]
+pub struct Crc32c(Crc32cState);
+
+impl Crc32c {
+    fn digest_bytes(&self) -> [u8; 4] {
+        self.0.0.to_be_bytes()
+    }
+}
+
+#[derive(Clone, Default)]
+struct Crc32cState(u32);
+
+impl Crc32cState {
+    fn update(&mut self, data: &[u8]) {
+        self.0 = crc32c::crc32c_append(self.0, data);
+    }
+
+    fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+/// Implement the `digest` traits for an adapter with a `digest_bytes` method,
+/// wrapping a state with `update` and `reset` methods.
+macro_rules! impl_digest {
+    ($name:ident, $size:ty) => {
+        impl HashMarker for $name {}
+
+        impl OutputSizeUser for $name {
+            type OutputSize = $size;
+        }
+
+        impl Update for $name {
+            fn update(&mut self, data: &[u8]) {
+                self.0.update(data);
+            }
+        }
+
+        impl FixedOutput for $name {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.digest_bytes());
+            }
+        }
+
+        impl FixedOutputReset for $name {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                out.copy_from_slice(&self.digest_bytes());
+                Reset::reset(self);
+            }
+        }
+
+        impl Reset for $name {
+            fn reset(&mut self) {
+                self.0.reset();
+            }
+        }
+    };
+}
+
+impl_digest!(Blake3, U32);
+impl_digest!(Xxh3_64, U8);
+impl_digest!(Xxh3_128, U16);
+impl_digest!(Crc32c, U4);
@@ -1,20 +1,91 @@
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Result};
+use std::path::Path;
 
+use blake2::Blake2b512;
 use clap::ValueEnum;
-use digest::Digest;
+use data_encoding::{BASE32, BASE64, BASE64URL_NOPAD, HEXUPPER};
+use digest::core_api::BlockSizeUser;
+use digest::{Digest, DynDigest, FixedOutputReset, KeyInit, Reset};
+use hmac::SimpleHmac;
 use md5::Md5;
 use sha1::Sha1;
 use sha2::{Sha256, Sha512};
 use sha3::{Sha3_256, Sha3_512};
 
+use crate::utils::digests::{Blake3, Crc32c, Xxh3_64, Xxh3_128};
+
+/// The size of the buffer used to hash a reader, so the memory used doesn't depend on its size.
+pub const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
 #[derive(Debug, ValueEnum, Clone, PartialEq)]
 pub enum HashAlgorithm {
+    /// Not cryptographically secure
     Md5,
+    /// Not cryptographically secure
     Sha1,
+    /// Cryptographically secure
     Sha2_256,
+    /// Cryptographically secure
     Sha2_512,
+    /// Cryptographically secure
     Sha3_256,
+    /// Cryptographically secure
     Sha3_512,
+    /// Cryptographically secure, fast
+    Blake2b,
+    /// Cryptographically secure, fast and multithreaded for files
+    Blake3,
+    /// Not cryptographically secure, very fast
+    Xxh3_64,
+    /// Not cryptographically secure, very fast
+    Xxh3_128,
+    /// Not cryptographically secure, very fast
+    Crc32c,
+}
+
+/// How a digest is printed.
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Default)]
+pub enum DigestEncoding {
+    /// Lowercase hexadecimal
+    #[default]
+    Hex,
+    /// Uppercase hexadecimal
+    UpperHex,
+    /// Standard base64, with padding (Content-MD5 headers)
+    Base64,
+    /// URL-safe base64, without padding
+    Base64url,
+    /// Standard base32, with padding
+    Base32,
+    /// Subresource integrity, `sha256-<base64>` (SHA2-256 and SHA2-512 only)
+    Sri,
+}
+
+impl DigestEncoding {
+    /// Returns true if the digests of the algorithm can be printed with the encoding.
+    pub fn supports(&self, algorithm: &HashAlgorithm) -> bool {
+        *self != DigestEncoding::Sri || algorithm.sri_prefix().is_some()
+    }
+
+    /// Encode the digest computed with the algorithm.
+    pub fn encode(&self, algorithm: &HashAlgorithm, digest: &[u8]) -> String {
+        match self {
+            DigestEncoding::Hex => hex::encode(digest),
+            DigestEncoding::UpperHex => HEXUPPER.encode(digest),
+            DigestEncoding::Base64 => BASE64.encode(digest),
+            DigestEncoding::Base64url => BASE64URL_NOPAD.encode(digest),
+            DigestEncoding::Base32 => BASE32.encode(digest),
+            DigestEncoding::Sri => format!(
+                "{}-{}",
+                algorithm
+                    .sri_prefix()
+                    .unwrap_or(&algorithm.to_string().to_lowercase()),
+                BASE64.encode(digest)
+            ),
+        }
+    }
 }
 
 impl Display for HashAlgorithm {
@@ -26,27 +97,239 @@ impl Display for HashAlgorithm {
             HashAlgorithm::Sha2_512 => write!(f, "SHA2-512"),
             HashAlgorithm::Sha3_256 => write!(f, "SHA3-256"),
             HashAlgorithm::Sha3_512 => write!(f, "SHA3-512"),
+            HashAlgorithm::Blake2b => write!(f, "BLAKE2b-512"),
+            HashAlgorithm::Blake3 => write!(f, "BLAKE3"),
+            HashAlgorithm::Xxh3_64 => write!(f, "XXH3-64"),
+            HashAlgorithm::Xxh3_128 => write!(f, "XXH3-128"),
+            HashAlgorithm::Crc32c => write!(f, "CRC32C"),
         }
     }
 }
 
 impl HashAlgorithm {
-    pub fn compute(&self, buffer: impl AsRef<[u8]>) -> Vec<u8> {
+    /// The algorithms that can be named in a manifest.
+    pub const ALL: [HashAlgorithm; 11] = [
+        HashAlgorithm::Md5,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha2_256,
+        HashAlgorithm::Sha2_512,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Sha3_512,
+        HashAlgorithm::Blake2b,
+        HashAlgorithm::Blake3,
+        HashAlgorithm::Xxh3_64,
+        HashAlgorithm::Xxh3_128,
+        HashAlgorithm::Crc32c,
+    ];
+
+    /// Returns the tag of the algorithm in the BSD checksum format, as written by `shasum --tag`.
+    pub fn bsd_tag(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA1",
+            HashAlgorithm::Sha2_256 => "SHA256",
+            HashAlgorithm::Sha2_512 => "SHA512",
+            HashAlgorithm::Sha3_256 => "SHA3-256",
+            HashAlgorithm::Sha3_512 => "SHA3-512",
+            HashAlgorithm::Blake2b => "BLAKE2b",
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Xxh3_64 => "XXH3",
+            HashAlgorithm::Xxh3_128 => "XXH128",
+            HashAlgorithm::Crc32c => "CRC32C",
+        }
+    }
+
+    /// Returns the prefix of the algorithm in a subresource integrity string, if it is allowed there.
+    pub fn sri_prefix(&self) -> Option<&'static str> {
+        match self {
+            HashAlgorithm::Sha2_256 => Some("sha256"),
+            HashAlgorithm::Sha2_512 => Some("sha512"),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the algorithm resists intentional collisions, so it can be trusted
+    /// to detect a malicious modification and not only an accidental corruption.
+    pub fn is_cryptographically_secure(&self) -> bool {
+        !matches!(
+            self,
+            HashAlgorithm::Md5
+                | HashAlgorithm::Sha1
+                | HashAlgorithm::Xxh3_64
+                | HashAlgorithm::Xxh3_128
+                | HashAlgorithm::Crc32c
+        )
+    }
+
+    /// Returns the label of the algorithm about its security.
+    pub fn security_label(&self) -> &'static str {
+        if self.is_cryptographically_secure() {
+            "cryptographically secure"
+        } else {
+            "not cryptographically secure"
+        }
+    }
+
+    /// Print a warning if the algorithm is used for an integrity check but isn't cryptographically secure.
+    pub fn warn_if_insecure(&self) {
+        if !self.is_cryptographically_secure() {
+            eprintln!(
+                "Warning: {self} is {}, it only detects accidental corruption and not intentional modifications.",
+                self.security_label()
+            );
+        }
+    }
+
+    /// Returns the algorithm with the given display name or BSD tag, ignoring the case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| {
+            algorithm.to_string().eq_ignore_ascii_case(name)
+                || algorithm.bsd_tag().eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Returns the size of the digests of the algorithm, in bytes.
+    pub fn output_size(&self) -> usize {
+        self.hasher().output_size()
+    }
+
+    /// Returns a new incremental hasher for the algorithm.
+    pub fn hasher(&self) -> Box<dyn DynDigest + Send> {
+        match self {
+            HashAlgorithm::Md5 => Box::new(Md5::new()),
+            HashAlgorithm::Sha1 => Box::new(Sha1::new()),
+            HashAlgorithm::Sha2_256 => Box::new(Sha256::new()),
+            HashAlgorithm::Sha2_512 => Box::new(Sha512::new()),
+            HashAlgorithm::Sha3_256 => Box::new(Sha3_256::new()),
+            HashAlgorithm::Sha3_512 => Box::new(Sha3_512::new()),
+            HashAlgorithm::Blake2b => Box::new(Blake2b512::new()),
+            HashAlgorithm::Blake3 => Box::new(Blake3::default()),
+            HashAlgorithm::Xxh3_64 => Box::new(Xxh3_64::default()),
+            HashAlgorithm::Xxh3_128 => Box::new(Xxh3_128::default()),
+            HashAlgorithm::Crc32c => Box::new(Crc32c::default()),
+        }
+    }
+
+    /// Returns a new incremental hasher keyed with the key: HMAC for the cryptographic hashes,
+    /// and the native keyed mode for BLAKE3, which needs a 32 bytes key.
+    /// Returns an error if the algorithm can't be keyed.
+    pub fn keyed_hasher(
+        &self,
+        key: &[u8],
+    ) -> std::result::Result<Box<dyn DynDigest + Send>, String> {
+        match self {
+            HashAlgorithm::Md5 => Self::hmac::<Md5>(key),
+            HashAlgorithm::Sha1 => Self::hmac::<Sha1>(key),
+            HashAlgorithm::Sha2_256 => Self::hmac::<Sha256>(key),
+            HashAlgorithm::Sha2_512 => Self::hmac::<Sha512>(key),
+            HashAlgorithm::Sha3_256 => Self::hmac::<Sha3_256>(key),
+            HashAlgorithm::Sha3_512 => Self::hmac::<Sha3_512>(key),
+            HashAlgorithm::Blake2b => Self::hmac::<Blake2b512>(key),
+            HashAlgorithm::Blake3 => {
+                let key: [u8; blake3::KEY_LEN] = key.try_into().map_err(|_| {
+                    format!(
+                        "BLAKE3 keyed hashing needs a key of exactly {} bytes, got {}",
+                        blake3::KEY_LEN,
+                        key.len()
+                    )
+                })?;
+
+                Ok(Box::new(Blake3(blake3::Hasher::new_keyed(&key))))
+            }
+            HashAlgorithm::Xxh3_64 | HashAlgorithm::Xxh3_128 | HashAlgorithm::Crc32c => {
+                Err(format!("{self} can't be used with a key"))
+            }
+        }
+    }
+
+    /// Returns the name of the algorithm when it is keyed, such as `HMAC-SHA2-256`.
+    pub fn keyed_name(&self) -> String {
         match self {
-            HashAlgorithm::Md5 => Self::compute_hash::<Md5>(buffer),
-            HashAlgorithm::Sha1 => Self::compute_hash::<Sha1>(buffer),
-            HashAlgorithm::Sha2_256 => Self::compute_hash::<Sha256>(buffer),
-            HashAlgorithm::Sha2_512 => Self::compute_hash::<Sha512>(buffer),
-            HashAlgorithm::Sha3_256 => Self::compute_hash::<Sha3_256>(buffer),
-            HashAlgorithm::Sha3_512 => Self::compute_hash::<Sha3_512>(buffer),
+            HashAlgorithm::Blake3 => format!("{self}-KEYED"),
+            _ => format!("HMAC-{self}"),
         }
     }
 
-    fn compute_hash<D: Digest>(buffer: impl AsRef<[u8]>) -> Vec<u8> {
-        let mut hasher = D::new();
-        hasher.update(&buffer);
+    fn hmac<D>(key: &[u8]) -> std::result::Result<Box<dyn DynDigest + Send>, String>
+    where
+        D: Digest + BlockSizeUser + FixedOutputReset + Reset + Clone + Send + 'static,
+    {
+        let mac = <SimpleHmac<D> as KeyInit>::new_from_slice(key).map_err(|e| e.to_string())?;
+
+        Ok(Box::new(mac))
+    }
+
+    pub fn compute(&self, buffer: impl AsRef<[u8]>) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(buffer.as_ref());
         hasher.finalize().to_vec()
     }
+
+    /// Compute the hash of everything read from the reader, chunk by chunk.
+    pub fn compute_reader(&self, reader: impl Read) -> Result<Vec<u8>> {
+        let mut hashes = Self::compute_reader_all(std::slice::from_ref(self), reader)?;
+
+        Ok(hashes.remove(0))
+    }
+
+    /// Compute the hashes of everything read from the reader with every algorithm,
+    /// reading it only once. The hashes are in the order of the algorithms.
+    pub fn compute_reader_all(
+        algorithms: &[HashAlgorithm],
+        reader: impl Read,
+    ) -> Result<Vec<Vec<u8>>> {
+        let mut hashers: Vec<_> = algorithms.iter().map(HashAlgorithm::hasher).collect();
+
+        update_hashers(&mut hashers, reader)?;
+
+        Ok(hashers
+            .into_iter()
+            .map(|hasher| hasher.finalize().to_vec())
+            .collect())
+    }
+
+    /// Compute the hash of the content of the file, without loading it in memory.
+    /// BLAKE3 maps the file in memory and hashes it with several threads.
+    pub fn compute_file(&self, path: &Path) -> Result<Vec<u8>> {
+        if *self == HashAlgorithm::Blake3 {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update_mmap_rayon(path)?;
+            return Ok(hasher.finalize().as_bytes().to_vec());
+        }
+
+        self.compute_reader(File::open(path)?)
+    }
+
+    /// Compute the hashes of the content of the file with every algorithm, reading it only once.
+    /// The hashes are in the order of the algorithms.
+    pub fn compute_file_all(algorithms: &[HashAlgorithm], path: &Path) -> Result<Vec<Vec<u8>>> {
+        if let [algorithm] = algorithms {
+            return Ok(vec![algorithm.compute_file(path)?]);
+        }
+
+        Self::compute_reader_all(algorithms, File::open(path)?)
+    }
+}
+
+/// Feed everything read from the reader to every hasher, chunk by chunk.
+pub fn update_hashers(
+    hashers: &mut [Box<dyn DynDigest + Send>],
+    mut reader: impl Read,
+) -> Result<()> {
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        for hasher in hashers.iter_mut() {
+            hasher.update(&buffer[..read]);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +352,125 @@ mod tests {
 
         assert_eq!(hash, expected, "Invalid hash value");
     }
+
+    #[rstest]
+    #[case(HashAlgorithm::Md5)]
+    #[case(HashAlgorithm::Sha1)]
+    #[case(HashAlgorithm::Sha2_512)]
+    #[case(HashAlgorithm::Sha3_256)]
+    fn test_compute_reader(#[case] algorithm: HashAlgorithm) {
+        let input = vec![42u8; HASH_BUFFER_SIZE * 2 + 7];
+
+        let hash = algorithm.compute_reader(input.as_slice()).unwrap();
+
+        assert_eq!(hash, algorithm.compute(&input));
+    }
+
+    #[rstest]
+    #[case(
+        HashAlgorithm::Blake2b,
+        "Hello, World!",
+        "7dfdb888af71eae0e6a6b751e8e3413d767ef4fa52a7993daa9ef097f7aa3d949199c113caa37c94f80cf3b22f7d9d6e4f5def4ff927830cffe4857c34be3d89"
+    )]
+    #[case(
+        HashAlgorithm::Blake3,
+        "",
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    )]
+    #[case(HashAlgorithm::Xxh3_64, "", "2d06800538d394c2")]
+    #[case(HashAlgorithm::Xxh3_128, "", "99aa06d3014798d86001c324468d497f")]
+    #[case(HashAlgorithm::Crc32c, "123456789", "e3069283")]
+    fn test_compute(#[case] algorithm: HashAlgorithm, #[case] input: &str, #[case] expected: &str) {
+        assert_eq!(encode(algorithm.compute(input)), expected);
+        assert_eq!(algorithm.output_size() * 2, expected.len());
+    }
+
+    #[test]
+    fn test_compute_file_blake3() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.bin");
+        let content = vec![7u8; 300_000];
+        std::fs::write(&path, &content).unwrap();
+
+        let hash = HashAlgorithm::Blake3.compute_file(&path).unwrap();
+
+        assert_eq!(hash, HashAlgorithm::Blake3.compute(&content));
+    }
+
+    #[rstest]
+    #[case(
+        HashAlgorithm::Sha2_256,
+        "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    )]
+    #[case(
+        HashAlgorithm::Sha2_512,
+        "b42af09057bac1e2d41708e48a902e09b5ff7f12ab428a4fe86653c73dd248fb82f948a549f7b791a5b41915ee4d1ec3935357e4e2317250d0372afa2ebeeb3a"
+    )]
+    #[case(
+        HashAlgorithm::Sha3_256,
+        "8c6e0683409427f8931711b10ca92a506eb1fafa48fadd66d76126f47ac2c333"
+    )]
+    fn test_keyed_hasher(#[case] algorithm: HashAlgorithm, #[case] expected: &str) {
+        let mut hasher = algorithm.keyed_hasher(b"key").unwrap();
+        hasher.update(b"The quick brown fox jumps over the lazy dog");
+
+        assert_eq!(encode(hasher.finalize()), expected);
+    }
+
+    #[test]
+    fn test_keyed_hasher_invalid() {
+        assert!(HashAlgorithm::Blake3.keyed_hasher(b"short").is_err());
+        assert!(HashAlgorithm::Blake3.keyed_hasher(&[0; 32]).is_ok());
+        assert!(HashAlgorithm::Crc32c.keyed_hasher(b"key").is_err());
+    }
+
+    #[rstest]
+    #[case(DigestEncoding::Hex, "9e107d9d372bb6826bd81d3542a419d6")]
+    #[case(DigestEncoding::UpperHex, "9E107D9D372BB6826BD81D3542A419D6")]
+    #[case(DigestEncoding::Base64, "nhB9nTcrtoJr2B01QqQZ1g==")]
+    #[case(DigestEncoding::Base64url, "nhB9nTcrtoJr2B01QqQZ1g")]
+    #[case(DigestEncoding::Base32, "TYIH3HJXFO3IE26YDU2UFJAZ2Y======")]
+    fn test_digest_encoding(#[case] encoding: DigestEncoding, #[case] expected: &str) {
+        let algorithm = HashAlgorithm::Md5;
+        let digest = algorithm.compute("The quick brown fox jumps over the lazy dog");
+
+        assert_eq!(encoding.encode(&algorithm, &digest), expected);
+    }
+
+    #[test]
+    fn test_digest_encoding_sri() {
+        let algorithm = HashAlgorithm::Sha2_256;
+
+        assert_eq!(
+            DigestEncoding::Sri.encode(&algorithm, &algorithm.compute("")),
+            "sha256-47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+        );
+        assert!(!DigestEncoding::Sri.supports(&HashAlgorithm::Md5));
+    }
+
+    #[test]
+    fn test_compute_reader_all() {
+        let algorithms = [
+            HashAlgorithm::Md5,
+            HashAlgorithm::Sha2_256,
+            HashAlgorithm::Sha3_512,
+        ];
+        let input = vec![1u8; HASH_BUFFER_SIZE + 3];
+
+        let hashes = HashAlgorithm::compute_reader_all(&algorithms, input.as_slice()).unwrap();
+
+        for (algorithm, hash) in algorithms.iter().zip(hashes) {
+            assert_eq!(hash, algorithm.compute(&input));
+        }
+    }
+
+    #[rstest]
+    #[case("SHA256", Some(HashAlgorithm::Sha2_256))]
+    #[case("sha2-256", Some(HashAlgorithm::Sha2_256))]
+    #[case("SHA3-512", Some(HashAlgorithm::Sha3_512))]
+    #[case("md5", Some(HashAlgorithm::Md5))]
+    #[case("SHA224", None)]
+    fn test_from_name(#[case] name: &str, #[case] expected: Option<HashAlgorithm>) {
+        assert_eq!(HashAlgorithm::from_name(name), expected);
+    }
 }
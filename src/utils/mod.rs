@@ -1,4 +1,5 @@
 pub mod alphabet;
+pub mod digests;
 pub mod hash;
 
 use rand::distr::{Alphanumeric, SampleString};
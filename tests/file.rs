@@ -1,5 +1,5 @@
 use std::{
-    fs::File,
+    fs::{File, create_dir_all},
     io::{Read, Write},
     path::Path,
     process::Command,
@@ -44,6 +44,112 @@ fn copy() {
     dest_dir.close().unwrap();
 }
 
+#[test]
+fn copy_removes_journal() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_root = tempdir().unwrap();
+    let dest_path = dest_root.path().join("dest");
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path
+    Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(&dest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(dest_path.join("file.txt").exists());
+    assert!(!dest_root.path().join(".dest.clixy-journal").exists());
+
+    src_dir.close().unwrap();
+    dest_root.close().unwrap();
+}
+
+#[test]
+fn copy_to_parent_destination() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_root = tempdir().unwrap();
+    let dest_path = dest_root.path().join("dest");
+    let work_path = dest_path.join("work");
+    create_dir_all(&work_path).unwrap();
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d .. -c, from dest_path/work
+    let output = Command::new(bin_path)
+        .current_dir(&work_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg("..")
+        .arg("-c")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(dest_path.join("file.txt").exists());
+    assert!(!dest_root.path().join(".dest.clixy-journal").exists());
+
+    src_dir.close().unwrap();
+    dest_root.close().unwrap();
+}
+
+#[test]
+fn copy_discards_stale_journal() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_root = tempdir().unwrap();
+    let dest_path = dest_root.path().join("dest");
+    let journal_path = dest_root.path().join(".dest.clixy-journal");
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let mut journal = File::create(&journal_path).unwrap();
+    journal.write_all(b"clixy-copy-journal 1\nstale\n").unwrap();
+    drop(journal);
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path
+    Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(&dest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(dest_path.join("file.txt").exists());
+    assert!(!journal_path.exists());
+
+    src_dir.close().unwrap();
+    dest_root.close().unwrap();
+}
+
 #[test]
 fn remove() {
     let dir = tempdir().unwrap();
@@ -76,6 +182,45 @@ fn remove() {
     dir.close().unwrap();
 }
 
+#[cfg(unix)]
+#[test]
+fn remove_skip_symlinks() {
+    let dir = tempdir().unwrap();
+    let src_path = dir.path().join("src");
+    let sub_dir_path = src_path.join("d");
+    create_dir_all(&sub_dir_path).unwrap();
+
+    let file_path = src_path.join("file.txt");
+    File::create(&file_path).unwrap();
+    let sub_file_path = sub_dir_path.join("file.txt");
+    File::create(&sub_file_path).unwrap();
+
+    let link_path = sub_dir_path.join("link.txt");
+    std::os::unix::fs::symlink("../file.txt", &link_path).unwrap();
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file remove -s src_path --symlinks skip -y
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("remove")
+        .arg("-s")
+        .arg(&src_path)
+        .arg("--symlinks")
+        .arg("skip")
+        .arg("-y")
+        .output()
+        .expect("Failed to execute command");
+
+    // The folders containing the skipped link are kept, without errors
+    assert!(output.stderr.is_empty());
+    assert!(!file_path.exists());
+    assert!(!sub_file_path.exists());
+    assert!(link_path.is_symlink());
+
+    dir.close().unwrap();
+}
+
 #[test]
 fn move_file() {
     let src_dir = tempdir().unwrap();
@@ -118,3 +263,386 @@ fn move_file() {
     src_dir.close().unwrap();
     dest_dir.close().unwrap();
 }
+
+#[cfg(unix)]
+#[test]
+fn copy_relative_symlink() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_dir = tempdir().unwrap();
+    let dest_path = dest_dir.path();
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    std::os::unix::fs::symlink("file.txt", src_path.join("link.txt")).unwrap();
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path
+    Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(dest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    let dest_link_path = dest_path.join("link.txt");
+
+    assert!(dest_link_path.is_symlink());
+    assert_eq!(
+        std::fs::read_link(&dest_link_path).unwrap(),
+        Path::new("file.txt")
+    );
+
+    src_dir.close().unwrap();
+    dest_dir.close().unwrap();
+}
+
+#[test]
+fn remove_dry_run() {
+    let dir = tempdir().unwrap();
+    let path = dir.path();
+
+    let file_path = path.join("file.txt");
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(b"Hello, world!").unwrap();
+    drop(file);
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file remove -s path --dry-run
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("remove")
+        .arg("-s")
+        .arg(path)
+        .arg("--dry-run")
+        .output()
+        .expect("Failed to execute command");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(file_path.exists());
+    assert!(stdout.contains("2 to delete (13 B)"));
+
+    dir.close().unwrap();
+}
+
+#[test]
+fn copy_mirror() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_dir = tempdir().unwrap();
+    let dest_path = dest_dir.path();
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let stale_file_path = dest_path.join("stale.txt");
+    File::create(&stale_file_path).unwrap();
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path --mirror -y
+    Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(dest_path)
+        .arg("--mirror")
+        .arg("-y")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(dest_path.join("file.txt").exists());
+    assert!(!stale_file_path.exists());
+
+    src_dir.close().unwrap();
+    dest_dir.close().unwrap();
+}
+
+#[test]
+fn copy_mirror_type_changed() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_dir = tempdir().unwrap();
+    let dest_path = dest_dir.path();
+
+    // "dir" is a folder in the source but a file in the destination, and "file" the opposite
+    create_dir_all(src_path.join("dir")).unwrap();
+    let mut src_file = File::create(src_path.join("dir").join("inner.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+    let mut src_file = File::create(src_path.join("file")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    File::create(dest_path.join("dir")).unwrap();
+    create_dir_all(dest_path.join("file").join("nested")).unwrap();
+    File::create(dest_path.join("file").join("nested").join("stale.txt")).unwrap();
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path --replace --mirror -y
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(dest_path)
+        .arg("--replace")
+        .arg("--mirror")
+        .arg("-y")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.stderr.is_empty());
+    assert!(dest_path.join("dir").join("inner.txt").is_file());
+    assert!(dest_path.join("file").is_file());
+
+    src_dir.close().unwrap();
+    dest_dir.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_mirror_skip_symlinks() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_dir = tempdir().unwrap();
+    let dest_path = dest_dir.path();
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+    std::os::unix::fs::symlink("file.txt", src_path.join("link.txt")).unwrap();
+
+    // The skipped links are not compared, so the destination ones are not extraneous
+    std::os::unix::fs::symlink("file.txt", dest_path.join("link.txt")).unwrap();
+    let stale_file_path = dest_path.join("stale.txt");
+    File::create(&stale_file_path).unwrap();
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path --mirror --symlinks skip -y
+    Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(dest_path)
+        .arg("--mirror")
+        .arg("--symlinks")
+        .arg("skip")
+        .arg("-y")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(dest_path.join("file.txt").exists());
+    assert!(dest_path.join("link.txt").is_symlink());
+    assert!(!stale_file_path.exists());
+
+    src_dir.close().unwrap();
+    dest_dir.close().unwrap();
+}
+
+#[test]
+fn copy_checksum() {
+    let src_dir = tempdir().unwrap();
+    let src_path = src_dir.path();
+
+    let dest_dir = tempdir().unwrap();
+    let dest_path = dest_dir.path();
+
+    let mut src_file = File::create(src_path.join("same.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let mut src_file = File::create(src_path.join("changed.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let mut dest_file = File::create(dest_path.join("same.txt")).unwrap();
+    dest_file.write_all(b"Hello, world!").unwrap();
+    drop(dest_file);
+
+    // Same size, different content
+    let mut dest_file = File::create(dest_path.join("changed.txt")).unwrap();
+    dest_file.write_all(b"Hello, World!").unwrap();
+    drop(dest_file);
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file copy -s src_path -d dest_path --checksum
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("copy")
+        .arg("-s")
+        .arg(src_path)
+        .arg("-d")
+        .arg(dest_path)
+        .arg("--checksum")
+        .output()
+        .expect("Failed to execute command");
+
+    let mut dest_content = String::new();
+    File::open(dest_path.join("changed.txt"))
+        .unwrap()
+        .read_to_string(&mut dest_content)
+        .unwrap();
+
+    assert_eq!(dest_content, "Hello, world!");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 files skipped as identical"));
+
+    src_dir.close().unwrap();
+    dest_dir.close().unwrap();
+}
+
+#[test]
+fn hash_dir_respect_gitignore() {
+    let dir = tempdir().unwrap();
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    let hash_dir = |name: &str, with_ignored: bool| {
+        let src_path = dir.path().join(name);
+        create_dir_all(src_path.join("target")).unwrap();
+
+        let mut gitignore = File::create(src_path.join(".gitignore")).unwrap();
+        gitignore.write_all(b"target/\n").unwrap();
+
+        let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+        src_file.write_all(b"Hello, world!").unwrap();
+
+        if with_ignored {
+            let mut ignored = File::create(src_path.join("target").join("build.out")).unwrap();
+            ignored.write_all(b"Build artifact").unwrap();
+        }
+
+        // clixy file hash src_path --respect-gitignore
+        let output = Command::new(bin_path)
+            .arg("file")
+            .arg("hash")
+            .arg(&src_path)
+            .arg("--respect-gitignore")
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let with_ignored = hash_dir("with_ignored", true);
+    let without_ignored = hash_dir("without_ignored", false);
+
+    assert!(!with_ignored.contains("build.out"));
+    assert!(with_ignored.contains("Tree digest: "));
+    assert_eq!(with_ignored, without_ignored);
+
+    dir.close().unwrap();
+}
+
+#[test]
+fn hash_manifest_check() {
+    let dir = tempdir().unwrap();
+    let src_path = dir.path().join("src");
+    std::fs::create_dir(&src_path).unwrap();
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, world!").unwrap();
+    drop(src_file);
+
+    let manifest_path = dir.path().join("SHA256SUMS");
+
+    let bin_path = Path::new(env!("CARGO_BIN_EXE_clixy"));
+
+    // clixy file hash src_path --manifest manifest_path
+    Command::new(bin_path)
+        .arg("file")
+        .arg("hash")
+        .arg(&src_path)
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    let mut manifest = String::new();
+    File::open(&manifest_path)
+        .unwrap()
+        .read_to_string(&mut manifest)
+        .unwrap();
+
+    assert!(
+        manifest.starts_with("315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3  ")
+    );
+
+    // clixy file hash --check manifest_path
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("hash")
+        .arg("--check")
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("file.txt: OK\n"));
+
+    let mut src_file = File::create(src_path.join("file.txt")).unwrap();
+    src_file.write_all(b"Hello, World!").unwrap();
+    drop(src_file);
+
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("hash")
+        .arg("--check")
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("file.txt: FAILED\n"));
+
+    // A listed path that can't be read is not reported as missing
+    std::fs::remove_file(src_path.join("file.txt")).unwrap();
+    std::fs::create_dir(src_path.join("file.txt")).unwrap();
+
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("hash")
+        .arg("--check")
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("file.txt: FAILED open or read\n"));
+
+    std::fs::remove_dir(src_path.join("file.txt")).unwrap();
+
+    let output = Command::new(bin_path)
+        .arg("file")
+        .arg("hash")
+        .arg("--check")
+        .arg(&manifest_path)
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("file.txt: MISSING\n"));
+
+    dir.close().unwrap();
+}